@@ -0,0 +1,208 @@
+//! Versioning of aggregates, whose root has to get a new version if one of its children changes.
+
+use diesel::{Connection, Identifiable, associations::BelongsTo, result::Error};
+#[cfg(feature = "async")]
+use diesel_async::AsyncConnection;
+#[cfg(feature = "async")]
+use diesel_async::scoped_futures::ScopedFutureExt;
+
+use crate::Versioned;
+#[cfg(feature = "async")]
+use crate::VersionedAsync;
+
+///
+/// Writes a versioned child of an aggregate and increments the version of its parent, the
+/// aggregate root, within the same transaction. So clients holding the parent get a conflict on
+/// their next write, and a stale parent rejects the change of the child.
+///
+/// The parent is touched by [`Versioned::touch_versioned`], so only its version is written. It has
+/// to be the parent referenced by the child through diesel's `#[diesel(belongs_to(...))]`.
+///
+/// Implemented for every [`Versioned`] entity belonging to a [`Versioned`] parent, both `Clone` to
+/// be restored on a rollback. To insert a new child, touch the parent within the transaction of the
+/// insert.
+///
+pub trait VersionedChild<PARENT, CONN> {
+    ///
+    /// Updates the child by [`Versioned::update_versioned`] and touches the parent. If the
+    /// transaction is rolled back, the child and the version of the parent are restored.
+    ///
+    fn update_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error>;
+
+    ///
+    /// Deletes the child by [`Versioned::delete_versioned`] and touches the parent. If the
+    /// transaction is rolled back, a soft deleted child and the version of the parent are
+    /// restored.
+    ///
+    fn delete_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error>;
+}
+
+impl<T, PARENT, CONN> VersionedChild<PARENT, CONN> for T
+where
+    CONN: Connection,
+    T: Versioned<CONN, CONN::Backend> + BelongsTo<PARENT> + Clone,
+    PARENT: Versioned<CONN, CONN::Backend> + Clone,
+    for<'a> &'a PARENT: Identifiable<Id = &'a T::ForeignKey>,
+{
+    fn update_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error> {
+        check_parent(self, parent)?;
+        let snapshot = self.clone();
+        // the touch of the parent is rolled back too, e.g. if the commit fails
+        let parent_snapshot = parent.clone();
+        let result = conn.transaction(|conn| {
+            self.update_versioned(conn)?;
+            parent.touch_versioned(conn)
+        });
+        if result.is_err() {
+            *self = snapshot;
+            *parent = parent_snapshot;
+        }
+        result
+    }
+
+    fn delete_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error> {
+        check_parent(self, parent)?;
+        // a soft delete increments the version of the child
+        let snapshot = self.clone();
+        // the touch of the parent is rolled back too, e.g. if the commit fails
+        let parent_snapshot = parent.clone();
+        let result = conn.transaction(|conn| {
+            self.delete_versioned(conn)?;
+            parent.touch_versioned(conn)
+        });
+        if result.is_err() {
+            *self = snapshot;
+            *parent = parent_snapshot;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+///
+/// Async version of [`VersionedChild`].
+///
+pub trait VersionedChildAsync<PARENT, CONN> {
+    ///
+    /// Updates the child by [`VersionedAsync::update_versioned`] and touches the parent. If the
+    /// transaction is rolled back, the child and the version of the parent are restored.
+    ///
+    fn update_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    ///
+    /// Deletes the child by [`VersionedAsync::delete_versioned`] and touches the parent. If the
+    /// transaction is rolled back, a soft deleted child and the version of the parent are
+    /// restored.
+    ///
+    fn delete_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+#[cfg(feature = "async")]
+impl<T, PARENT, CONN> VersionedChildAsync<PARENT, CONN> for T
+where
+    CONN: AsyncConnection + Send,
+    T: VersionedAsync<CONN, CONN::Backend> + BelongsTo<PARENT> + Clone + Send,
+    PARENT: VersionedAsync<CONN, CONN::Backend> + Clone + Send,
+    for<'a> &'a PARENT: Identifiable<Id = &'a T::ForeignKey>,
+{
+    async fn update_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error> {
+        check_parent(self, parent)?;
+        let snapshot = self.clone();
+        // the touch of the parent is rolled back too, e.g. if the commit fails
+        let parent_snapshot = parent.clone();
+        let child = &mut *self;
+        let touched = &mut *parent;
+        let result = conn
+            .transaction(|conn| {
+                async move {
+                    child.update_versioned(conn).await?;
+                    touched.touch_versioned(conn).await
+                }
+                .scope_boxed()
+            })
+            .await;
+        if result.is_err() {
+            *self = snapshot;
+            *parent = parent_snapshot;
+        }
+        result
+    }
+
+    async fn delete_versioned_with_parent(
+        &mut self,
+        parent: &mut PARENT,
+        conn: &mut CONN,
+    ) -> Result<(), Error> {
+        check_parent(self, parent)?;
+        // a soft delete increments the version of the child
+        let snapshot = self.clone();
+        // the touch of the parent is rolled back too, e.g. if the commit fails
+        let parent_snapshot = parent.clone();
+        let child = &mut *self;
+        let touched = &mut *parent;
+        let result = conn
+            .transaction(|conn| {
+                async move {
+                    child.delete_versioned(conn).await?;
+                    touched.touch_versioned(conn).await
+                }
+                .scope_boxed()
+            })
+            .await;
+        if result.is_err() {
+            *self = snapshot;
+            *parent = parent_snapshot;
+        }
+        result
+    }
+}
+
+///
+/// Checks, that the child references the parent, before anything is written.
+///
+fn check_parent<T, PARENT>(child: &T, parent: &PARENT) -> Result<(), Error>
+where
+    T: BelongsTo<PARENT>,
+    for<'a> &'a PARENT: Identifiable<Id = &'a T::ForeignKey>,
+{
+    if child.foreign_key() == Some(parent.id()) {
+        Ok(())
+    } else {
+        Err(Error::QueryBuilderError(
+            format!(
+                "{} doesn't belong to the given {}",
+                std::any::type_name::<T>(),
+                std::any::type_name::<PARENT>()
+            )
+            .into(),
+        ))
+    }
+}
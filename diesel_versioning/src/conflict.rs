@@ -0,0 +1,142 @@
+//! Classification of the errors reported by versioned writes.
+
+use std::fmt::Debug;
+
+use diesel::result::{DatabaseErrorInformation, DatabaseErrorKind, Error};
+
+/// Constraint name of a version conflict, which no constraint of a database is expected to have.
+const VERSION_CONSTRAINT: &str = "diesel_versioning::version";
+
+/// Constraint name of a conflict with a soft deleted row.
+const DELETED_CONSTRAINT: &str = "diesel_versioning::deleted";
+
+///
+/// Conflict of a versioned write. It is reported as `Error::DatabaseError` of kind
+/// `CheckViolation`, whose message starts with `optimistic locking:`.
+///
+/// ```ignore
+/// match entry.update_versioned(conn) {
+///     Err(err) if Conflict::of(&err) == Some(Conflict::Deleted) => { /* gone */ }
+///     Err(err) if Conflict::of(&err) == Some(Conflict::Version) => { /* reload and retry */ }
+///     result => result?,
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    /// The row was written or deleted since the entity was read.
+    Version,
+    /// The row is soft deleted by `#[version(soft_delete = ...)]`.
+    Deleted,
+}
+
+impl Conflict {
+    ///
+    /// Classifies `error`, `None` if it is no conflict of a versioned write.
+    ///
+    pub fn of(error: &Error) -> Option<Self> {
+        match error {
+            Error::DatabaseError(DatabaseErrorKind::CheckViolation, info) => {
+                match info.constraint_name() {
+                    Some(VERSION_CONSTRAINT) => Some(Conflict::Version),
+                    Some(DELETED_CONSTRAINT) => Some(Conflict::Deleted),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn constraint_name(self) -> &'static str {
+        match self {
+            Conflict::Version => VERSION_CONSTRAINT,
+            Conflict::Deleted => DELETED_CONSTRAINT,
+        }
+    }
+}
+
+///
+/// Information of a conflict. diesel's `DatabaseErrorInformation` can't be downcast, so the
+/// conflict is marked by the constraint name, independent of the message.
+///
+#[derive(Debug)]
+struct ConflictInformation {
+    message: String,
+    constraint: &'static str,
+}
+
+impl DatabaseErrorInformation for ConflictInformation {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn details(&self) -> Option<&str> {
+        None
+    }
+
+    fn hint(&self) -> Option<&str> {
+        None
+    }
+
+    fn table_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn column_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn constraint_name(&self) -> Option<&str> {
+        Some(self.constraint)
+    }
+
+    fn statement_position(&self) -> Option<i32> {
+        None
+    }
+}
+
+///
+/// Id of an entity, which is named in the message of a conflict. Only checked by the generated
+/// writes, so a missing `Debug` of the key is reported as such.
+///
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "the id `{Self}` of a versioned entity doesn't implement `Debug`",
+    label = "named in the conflict errors of versioned writes",
+    note = "the fields of the primary key have to implement `Debug`, e.g. by `#[derive(Debug)]`"
+)]
+pub trait ConflictId: Debug {}
+
+impl<T: Debug> ConflictId for T {}
+
+fn conflict_error(conflict: Conflict, message: String) -> Error {
+    Error::DatabaseError(
+        DatabaseErrorKind::CheckViolation,
+        Box::new(ConflictInformation {
+            message,
+            constraint: conflict.constraint_name(),
+        }),
+    )
+}
+
+///
+/// Error of a versioned write, which `operation` didn't write exactly one row.
+///
+#[doc(hidden)]
+pub fn version_conflict<I: ConflictId>(operation: &str, rows: usize, id: I) -> Error {
+    conflict_error(
+        Conflict::Version,
+        format!("optimistic locking: {operation} {rows} rows for id {id:?}. expected 1"),
+    )
+}
+
+///
+/// Error of a versioned write of a soft deleted row.
+///
+#[doc(hidden)]
+pub fn deleted_conflict<I: ConflictId>(id: I) -> Error {
+    conflict_error(
+        Conflict::Deleted,
+        format!("optimistic locking: id {id:?} is deleted"),
+    )
+}
@@ -0,0 +1,103 @@
+//! Object safe versions of [`Versioned`] and [`VersionedAsync`], e.g. to store different entities
+//! in one list and flush them together.
+
+#[cfg(feature = "async")]
+use std::pin::Pin;
+
+use diesel::{Connection, result::Error};
+#[cfg(feature = "async")]
+use diesel_async::AsyncConnection;
+
+use crate::Versioned;
+#[cfg(feature = "async")]
+use crate::VersionedAsync;
+
+///
+/// Object safe version of [`Versioned`], implemented for every [`Versioned`] entity. The methods
+/// are named differently, so calls are not ambiguous if both traits are in scope.
+///
+pub trait DynVersioned<CONN> {
+    ///
+    /// See [`Versioned::update_versioned`].
+    ///
+    fn update_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error>;
+
+    ///
+    /// See [`Versioned::touch_versioned`], which also defaults to the update.
+    ///
+    fn touch_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.update_versioned_dyn(conn)
+    }
+
+    ///
+    /// See [`Versioned::delete_versioned`].
+    ///
+    fn delete_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error>;
+}
+
+impl<T, CONN> DynVersioned<CONN> for T
+where
+    CONN: Connection,
+    T: Versioned<CONN, CONN::Backend>,
+{
+    fn update_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.update_versioned(conn)
+    }
+
+    fn touch_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.touch_versioned(conn)
+    }
+
+    fn delete_versioned_dyn(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.delete_versioned(conn)
+    }
+}
+
+#[cfg(feature = "async")]
+///
+/// Boxed future returned by [`DynVersionedAsync`].
+///
+pub type VersionedFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+#[cfg(feature = "async")]
+///
+/// Object safe version of [`VersionedAsync`], implemented for every [`VersionedAsync`] entity. The
+/// futures are boxed, as a trait object can't return `impl Future`.
+///
+pub trait DynVersionedAsync<CONN>: Send {
+    ///
+    /// See [`VersionedAsync::update_versioned`].
+    ///
+    fn update_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a>;
+
+    ///
+    /// See [`VersionedAsync::touch_versioned`], which also defaults to the update.
+    ///
+    fn touch_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a> {
+        self.update_versioned_dyn(conn)
+    }
+
+    ///
+    /// See [`VersionedAsync::delete_versioned`].
+    ///
+    fn delete_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a>;
+}
+
+#[cfg(feature = "async")]
+impl<T, CONN> DynVersionedAsync<CONN> for T
+where
+    CONN: AsyncConnection,
+    T: VersionedAsync<CONN, CONN::Backend> + Send,
+{
+    fn update_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a> {
+        Box::pin(self.update_versioned(conn))
+    }
+
+    fn touch_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a> {
+        Box::pin(self.touch_versioned(conn))
+    }
+
+    fn delete_versioned_dyn<'a>(&'a mut self, conn: &'a mut CONN) -> VersionedFuture<'a> {
+        Box::pin(self.delete_versioned(conn))
+    }
+}
@@ -0,0 +1,146 @@
+//! History of versioned entities, which keeps the row image of every replaced version.
+
+use diesel::{
+    Connection, QueryResult, RunQueryDsl,
+    backend::Backend,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_source::{QuerySource, Table},
+    result::Error,
+};
+#[cfg(feature = "async")]
+use diesel_async::AsyncConnection;
+
+///
+/// Reads the versions of an entity with `#[version(history = ...)]`. Implemented by the derive
+/// macro.
+///
+/// The history table holds a row for every version replaced by `update_versioned`,
+/// `touch_versioned` or deleted by `delete_versioned`, while the current version stays in the table
+/// of the entity.
+///
+pub trait VersionHistory<CONN, DB>: Sized
+where
+    CONN: Connection<Backend = DB>,
+    DB: Backend,
+{
+    /// Type of the version field.
+    type Version;
+
+    ///
+    /// Loads the entity with the id of `self` at `version`, from the history table or the table of
+    /// the entity if it is the current version. Fails with `NotFound` if there is no such version.
+    ///
+    fn load_version(&self, version: Self::Version, conn: &mut CONN) -> Result<Self, Error>;
+
+    ///
+    /// Loads the replaced versions of the entity with the id of `self` from the history table,
+    /// ordered by version.
+    ///
+    fn load_history(&self, conn: &mut CONN) -> Result<Vec<Self>, Error>;
+
+    ///
+    /// Writes the entity at `version` back as a new version, e.g. to undo changes. The version of
+    /// `self` is checked against the current one like by `update_versioned`, and `self` is replaced
+    /// by the restored entity. Every column is written, also the `NULL` ones skipped by
+    /// `AsChangeset`.
+    ///
+    fn restore_to_version(&mut self, version: Self::Version, conn: &mut CONN) -> Result<(), Error>;
+}
+
+#[cfg(feature = "async")]
+///
+/// Async version of [`VersionHistory`].
+///
+pub trait VersionHistoryAsync<CONN, DB>: Sized
+where
+    CONN: AsyncConnection<Backend = DB>,
+    DB: Backend,
+{
+    /// Type of the version field.
+    type Version;
+
+    ///
+    /// Loads the entity with the id of `self` at `version`, from the history table or the table of
+    /// the entity if it is the current version. Fails with `NotFound` if there is no such version.
+    ///
+    fn load_version(
+        &self,
+        version: Self::Version,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<Self, Error>> + Send;
+
+    ///
+    /// Loads the replaced versions of the entity with the id of `self` from the history table,
+    /// ordered by version.
+    ///
+    fn load_history(
+        &self,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<Vec<Self>, Error>> + Send;
+
+    ///
+    /// Writes the entity at `version` back as a new version, e.g. to undo changes. The version of
+    /// `self` is checked against the current one like by `update_versioned`, and `self` is replaced
+    /// by the restored entity. Every column is written, also the `NULL` ones skipped by
+    /// `AsChangeset`.
+    ///
+    fn restore_to_version(
+        &mut self,
+        version: Self::Version,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+///
+/// `INSERT INTO history (columns) SELECT ...`, copying the current row image of an entity into its
+/// history table. diesel's own insert from a select can't be named in the bounds of the generated
+/// code.
+///
+#[doc(hidden)]
+#[derive(Debug, Clone)]
+pub struct CopyToHistory<H: QuerySource, S> {
+    into: H::FromClause,
+    columns: &'static [&'static str],
+    select: S,
+}
+
+impl<H: Table, S> CopyToHistory<H, S> {
+    /// Copies the rows selected by `select` into the `columns` of the history table `into`.
+    pub fn new(into: H, columns: &'static [&'static str], select: S) -> Self {
+        Self {
+            into: into.from_clause(),
+            columns,
+            select,
+        }
+    }
+}
+
+impl<H: QuerySource, S> QueryId for CopyToHistory<H, S> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<H: QuerySource, S, CONN> RunQueryDsl<CONN> for CopyToHistory<H, S> {}
+
+impl<DB, H, S> QueryFragment<DB> for CopyToHistory<H, S>
+where
+    DB: Backend,
+    H: QuerySource,
+    H::FromClause: QueryFragment<DB>,
+    S: QueryFragment<DB>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, DB>) -> QueryResult<()> {
+        out.push_sql("INSERT INTO ");
+        self.into.walk_ast(out.reborrow())?;
+        out.push_sql(" (");
+        for (index, column) in self.columns.iter().enumerate() {
+            if index > 0 {
+                out.push_sql(", ");
+            }
+            out.push_identifier(column)?;
+        }
+        out.push_sql(") ");
+        self.select.walk_ast(out.reborrow())
+    }
+}
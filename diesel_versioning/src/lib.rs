@@ -5,7 +5,9 @@
 //! locking.
 //!
 //! The entity must have implemented [`diesel::AsChangeset`] and [`diesel::Identifiable`] to implement [`Versioned`]. You can
-//! use the provided derive macro.
+//! use the provided derive macro. The id has to implement `Debug`, like diesel requires of the types
+//! of keys, as it is named in the errors of conflicts, e.g. the full tuple `(tenant_id, id)` of a
+//! composite primary key.
 //!
 //! ```ignore
 //! use diesel::AsChangeset;
@@ -23,10 +25,91 @@
 //! }
 //! ```
 //!
-//! Currently only integer values are supported as version field.
+//! Without `#[diesel(check_for_backend(...))]` a single implementation generic over the backend is
+//! derived, which is usable with every backend supporting the generated statements.
 //!
-//! If you use the feature-flag `async`, you have to use [`VersionedAsync`] instead of
-//! [`Versioned`].
+//! MySQL reports the number of changed instead of matched rows, unless the connection uses
+//! `CLIENT_FOUND_ROWS` (as diesel and diesel-async do). As the version is incremented on every update,
+//! an update without any other change still counts as updated row on MySQL.
+//!
+//! The version field has to be of type `i16`, `i32` or `i64`, matching a `SmallInt`, `Integer` or
+//! `BigInt` column. A version column missing in the `table!` or of another SQL type is reported at
+//! the version field.
+//!
+//! A nullable version column can be mapped by an `Option` version field. `None` marks a row which was
+//! never versioned, it is matched by `IS NULL` and the update sets the version to the initial value
+//! configured by `#[version(initial = ...)]`, which defaults to `1`.
+//!
+//! With the feature-flag `async` the derive additionally implements [`VersionedAsync`], so the same
+//! entity can be used with sync and async connections.
+//!
+//! To handle different entities by trait objects, use [`DynVersioned`] or [`DynVersionedAsync`],
+//! which are implemented for every versioned entity.
+//!
+//! To write many entities atomically, register them at a [`UnitOfWork`] (or `UnitOfWorkAsync`) and
+//! commit it. All inserts, updates and deletes run in one transaction. On a failure the transaction
+//! is rolled back, the versions of the updated entities are restored and the error names the failed
+//! operation.
+//!
+//! If a child of an aggregate changes, the version of its root can be incremented in the same
+//! transaction by [`VersionedChild`] (or `VersionedChildAsync`). The parent is resolved through
+//! diesel's `#[diesel(belongs_to(...))]` and only its version column is written by
+//! [`Versioned::touch_versioned`], which is guarded by the expected version like every other
+//! statement.
+//!
+//! For Postgres tables which can't get a version column, the system column `xmin` can be used as
+//! version by `#[version(xmin)]` on a field of type `pg::TransactionId`, with the feature-flag
+//! `postgres`. The column has to be added to the `table!` as `xmin -> Xid`, using
+//! `pg::sql_types::Xid`. The update writes all fields but the primary key explicitly, as Postgres
+//! doesn't allow to set `xmin`, and reads the new `xmin` by `RETURNING xmin`. As `xmin` is the id of
+//! the writing transaction, a row written twice within one transaction keeps its `xmin` after the
+//! first write. `None` is written as `NULL`, so `#[diesel(treat_none_as_null)]`,
+//! `#[diesel(skip_update)]` and `#[diesel(serialize_as)]` are rejected for such an entity.
+//!
+//! Writers bypassing diesel, e.g. psql or other services, may update a row without incrementing its
+//! version. [`migration::VersionTrigger`] generates the SQL of a Postgres or SQLite trigger for a
+//! migration, which rejects such updates or increments the version instead.
+//!
+//! With `#[version(history = simple_history)]` every version replaced by `update_versioned` or
+//! `touch_versioned`, and the version deleted by `delete_versioned`, is copied into the history
+//! table within the same transaction. The history table has the columns of the table with the same
+//! names and types, identified by the primary key and the version. Further columns need a default,
+//! e.g. the time a version was replaced by `recorded_at TIMESTAMP NOT NULL DEFAULT
+//! CURRENT_TIMESTAMP`. An actor is recorded by a column of the entity, e.g. `updated_by`.
+//! [`VersionHistory::load_version`] loads an entity at a given version and
+//! [`VersionHistory::load_history`] all its replaced versions, which requires a `Queryable` entity.
+//! [`VersionHistory::restore_to_version`] writes a loaded version back as new version, checked
+//! against the version of the entity like any update, e.g. to undo changes.
+//!
+//! With `#[version(soft_delete = deleted_at)]` the entity is soft deleted: `delete_versioned` sets
+//! the nullable timestamp column `deleted_at` to `now` and increments the version, instead of
+//! deleting the row. Updates of a soft deleted row fail, and [`Conflict::of`] tells this
+//! [`Conflict::Deleted`] apart from a [`Conflict::Version`] of a stale entity.
+//! [`SoftDelete::undelete_versioned`] clears the column again as new version, also setting a field
+//! of the column to `None`. A field of the column isn't set by `delete_versioned`, as `now` is the
+//! time of the database, so it stays `None` until the entity is loaded again. Queries of the entity
+//! have to filter the soft deleted rows themselves, e.g. by `deleted_at.is_null()`.
+//!
+//! With `#[version(outbox = schema::outbox)]` and the feature-flag `outbox`, every versioned write
+//! inserts an event into the outbox table, in the same transaction as the write. The table has the
+//! columns `entity_type` (the name of the struct), `entity_id` and `payload` (the id and the entity
+//! serialized as JSON by serde) as `Text`, `version` as `BigInt` and `operation` as `Text`, which is
+//! `update`, `touch`, `delete` or `undelete`. The version of the event is the one written, of a
+//! delete the following one, so the versions of an entity are the sequence of its events. No event
+//! is written if the write fails.
+//!
+//! To version an existing table, [`migration::AddVersionColumn`] generates the SQL adding the
+//! version column, which backfills the existing rows with an initial version.
+//! [`migration::SqlMigration`] runs generated SQL by the `MigrationHarness` of diesel_migrations,
+//! next to the embedded migrations.
+//!
+//! [`lint::VersionLint`] lists the tables of a live SQLite or Postgres database, whose version
+//! column is missing, nullable or without default, with the feature-flags `sqlite` or `postgres`.
+//! The binary `diesel_versioning_lint` of the workspace prints them, e.g. to audit the adoption in
+//! CI.
+//!
+//! The generated code only uses fully qualified paths. If diesel_versioning is used through a crate
+//! re-exporting it, its path can be set by `#[diesel_versioning(crate = "my_facade::versioning")]`.
 
 use diesel::Connection;
 use diesel::{AsChangeset, result::Error};
@@ -34,11 +117,43 @@ use diesel::{AsChangeset, result::Error};
 #[cfg(feature = "async")]
 use diesel_async::AsyncConnection;
 
+mod aggregate;
+mod conflict;
+mod dynamic;
+mod history;
+pub mod lint;
+pub mod migration;
+#[cfg(feature = "outbox")]
+mod outbox;
+#[cfg(feature = "postgres")]
+pub mod pg;
+mod soft_delete;
+mod transaction;
+mod unit_of_work;
+
+pub use aggregate::VersionedChild;
+#[cfg(feature = "async")]
+pub use aggregate::VersionedChildAsync;
+pub use conflict::Conflict;
+pub use dynamic::DynVersioned;
+#[cfg(feature = "async")]
+pub use dynamic::{DynVersionedAsync, VersionedFuture};
+pub use history::VersionHistory;
+#[cfg(feature = "async")]
+pub use history::VersionHistoryAsync;
+pub use soft_delete::SoftDelete;
+#[cfg(feature = "async")]
+pub use soft_delete::SoftDeleteAsync;
+#[cfg(feature = "async")]
+pub use unit_of_work::UnitOfWorkAsync;
+pub use unit_of_work::{FailedOperation, OperationKind, UnitOfWork, UnitOfWorkError};
+
 ///
 /// Trait that must be implemented by an entity, to support optimitsic locking. You would use the
-/// provided derive macro.
+/// provided derive macro, which requires the id of the entity to implement `Debug`.
 ///
-/// If you want to use async connection, use [`VersionedAsync`] instead.
+/// For async connections use [`VersionedAsync`], which is implemented by the same derive macro if
+/// the feature `async` is enabled.
 ///
 pub trait Versioned<CONN, DB>: AsChangeset
 where
@@ -51,6 +166,18 @@ where
     ///
     fn update_versioned(&mut self, conn: &mut CONN) -> Result<(), Error>;
 
+    ///
+    /// Increments only the version of the entity, without writing its other fields, e.g. to mark
+    /// an aggregate root as changed if one of its children changes. The version field will be
+    /// checked.
+    ///
+    /// The derive macro only writes the version. By default the entity is written unchanged by
+    /// [`Versioned::update_versioned`], so manual implementations don't have to provide it.
+    ///
+    fn touch_versioned(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.update_versioned(conn)
+    }
+
     ///
     /// Deletes the entity using the provided connection. The version field will be checked.
     ///
@@ -62,7 +189,8 @@ where
 /// Trait that must be implemented by an entity, to support optimitsic locking. You would use the
 /// provided derive macro.
 ///
-/// This is the async version of [`Versioned`]
+/// This is the async version of [`Versioned`]. The returned futures are `Send`, so they can be
+/// used within `tokio::spawn` or handlers of a multi-threaded runtime, also for a generic connection.
 ///
 pub trait VersionedAsync<CONN, DB>: AsChangeset
 where
@@ -73,14 +201,451 @@ where
     /// Updates the entity using the provided connection. The version field will be checked and
     /// incremented.
     ///
-    fn update_versioned(&mut self, conn: &mut CONN) -> impl Future<Output = Result<(), Error>>;
+    fn update_versioned(
+        &mut self,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    ///
+    /// Increments only the version of the entity, without writing its other fields, e.g. to mark
+    /// an aggregate root as changed if one of its children changes. The version field will be
+    /// checked.
+    ///
+    /// The derive macro only writes the version. By default the entity is written unchanged by
+    /// [`VersionedAsync::update_versioned`], so manual implementations don't have to provide it.
+    ///
+    fn touch_versioned(
+        &mut self,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        self.update_versioned(conn)
+    }
 
     ///
     /// Deletes the entity using the provided connection. The version field will be checked.
     ///
-    fn delete_versioned(&mut self, conn: &mut CONN) -> impl Future<Output = Result<(), Error>>;
+    fn delete_versioned(
+        &mut self,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Items used by the code generated by the derive macros. They are not part of the public
+    //! API.
+
+    #[cfg(feature = "async")]
+    use diesel_async::AsyncConnection;
+
+    pub use diesel;
+    #[cfg(feature = "async")]
+    pub use diesel_async;
+
+    /// Expands the async impls generated by the derive macro only if the feature `async` of this
+    /// crate is enabled, independent of the features the derive crate is compiled with.
+    pub use crate::__if_async as if_async;
+
+    pub use crate::conflict::{ConflictId, deleted_conflict, version_conflict};
+    pub use crate::history::CopyToHistory;
+    #[cfg(feature = "outbox")]
+    pub use crate::outbox::{EventVersion, check_outbox_table, to_json};
+    pub use crate::transaction::write_in_transaction;
+    #[cfg(feature = "async")]
+    pub use crate::transaction::write_in_transaction_async;
+    #[cfg(feature = "outbox")]
+    pub use serde;
+
+    ///
+    /// Statements of a versioned entity, implemented by the derive macro for a reference to the
+    /// entity.
+    ///
+    pub trait VersionedStatements<CONN, V> {
+        fn update_with_version(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        fn delete_with_version(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        fn touch_with_version(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+    }
+
+    ///
+    /// Async version of [`VersionedStatements`].
+    ///
+    #[cfg(feature = "async")]
+    pub trait VersionedStatementsAsync<'query, CONN: AsyncConnection, V> {
+        fn update_with_version<'conn>(
+            self,
+            expected_version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        fn delete_with_version<'conn>(
+            self,
+            expected_version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        fn touch_with_version<'conn>(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+    }
+
+    ///
+    /// Checks, that the type of a version field matches the SQL type `ST` of its column.
+    ///
+    #[diagnostic::on_unimplemented(
+        message = "the version `{Self}` doesn't match the SQL type `{ST}` of its column",
+        label = "version of the wrong type",
+        note = "a version of `i16`, `i32` or `i64` requires a column of `SmallInt`, `Integer` or `BigInt`, an `Option` of it a `Nullable` column"
+    )]
+    pub trait VersionSqlType<ST> {}
+
+    impl VersionSqlType<diesel::sql_types::SmallInt> for i16 {}
+
+    impl VersionSqlType<diesel::sql_types::Integer> for i32 {}
+
+    impl VersionSqlType<diesel::sql_types::BigInt> for i64 {}
+
+    impl<T, ST> VersionSqlType<diesel::sql_types::Nullable<ST>> for Option<T> where T: VersionSqlType<ST>
+    {}
+
+    #[cfg(feature = "postgres")]
+    impl VersionSqlType<crate::pg::sql_types::Xid> for crate::pg::TransactionId {}
+
+    /// Static assertion of the version column `C`, emitted by the derive macro.
+    pub const fn check_version_column<C, V>()
+    where
+        C: diesel::Column,
+        V: VersionSqlType<C::SqlType>,
+    {
+    }
+
+    ///
+    /// Bind of the version `V` to a statement on a column of the SQL type `ST`. Unlike a bind of
+    /// diesel, its type doesn't require the version to match `ST`. So the statements of a wrong
+    /// version column are well-formed until they are executed, and the column is reported by
+    /// [`check_version_column`] alone.
+    ///
+    #[derive(Debug, Clone, Copy)]
+    pub struct VersionBind<ST, V> {
+        version: V,
+        sql_type: std::marker::PhantomData<ST>,
+    }
+
+    /// Binds a version to a statement on the column `C`, see [`VersionBind`].
+    pub fn version_bind<C, V>(version: V) -> VersionBind<C::SqlType, V>
+    where
+        C: diesel::Expression,
+    {
+        VersionBind {
+            version,
+            sql_type: std::marker::PhantomData,
+        }
+    }
+
+    impl<ST, V> diesel::Expression for VersionBind<ST, V>
+    where
+        ST: diesel::sql_types::SqlType + diesel::expression::TypedExpressionType,
+    {
+        type SqlType = ST;
+    }
+
+    impl<ST, V, DB> diesel::query_builder::QueryFragment<DB> for VersionBind<ST, V>
+    where
+        DB: diesel::backend::Backend + diesel::sql_types::HasSqlType<ST>,
+        V: diesel::serialize::ToSql<ST, DB>,
+    {
+        fn walk_ast<'b>(
+            &'b self,
+            mut pass: diesel::query_builder::AstPass<'_, 'b, DB>,
+        ) -> diesel::QueryResult<()> {
+            pass.push_bind_param(&self.version)
+        }
+    }
+
+    impl<ST, V> diesel::query_builder::QueryId for VersionBind<ST, V>
+    where
+        ST: diesel::query_builder::QueryId,
+    {
+        type QueryId = VersionBind<ST::QueryId, ()>;
+
+        const HAS_STATIC_QUERY_ID: bool = ST::HAS_STATIC_QUERY_ID;
+    }
+
+    impl<ST, V, QS> diesel::SelectableExpression<QS> for VersionBind<ST, V> where
+        Self: diesel::AppearsOnTable<QS>
+    {
+    }
+
+    impl<ST, V, QS> diesel::AppearsOnTable<QS> for VersionBind<ST, V> where Self: diesel::Expression {}
+
+    impl<ST, V, GB> diesel::expression::ValidGrouping<GB> for VersionBind<ST, V> {
+        type IsAggregate = diesel::expression::is_aggregate::Never;
+    }
+
+    /// Static assertion, that the column `H` of the history table matches the column `C` of the
+    /// entity, emitted by the derive macro.
+    pub const fn check_history_column<C, H>()
+    where
+        C: diesel::Column,
+        H: diesel::Column<SqlType = C::SqlType>,
+    {
+    }
+
+    ///
+    /// Statements of an entity with a history table, implemented by the derive macro for a
+    /// reference to the entity `T`.
+    ///
+    pub trait HistoryStatements<CONN, V, T> {
+        /// Copies the row image of `expected_version` into the history table.
+        fn copy_with_version(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        fn load_version(self, version: V, conn: &mut CONN) -> diesel::QueryResult<T>;
+
+        fn load_history(self, conn: &mut CONN) -> diesel::QueryResult<Vec<T>>;
+
+        /// Writes every column but the primary key, also the `NULL` ones skipped by `AsChangeset`.
+        fn restore_with_version(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+    }
+
+    ///
+    /// Async version of [`HistoryStatements`].
+    ///
+    #[cfg(feature = "async")]
+    pub trait HistoryStatementsAsync<'query, CONN: AsyncConnection, V, T> {
+        /// Copies the row image of `expected_version` into the history table.
+        fn copy_with_version<'conn>(
+            self,
+            expected_version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        fn load_version(
+            self,
+            version: V,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<T>> + Send;
+
+        fn load_history(
+            self,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<Vec<T>>> + Send;
+
+        /// Writes every column but the primary key, also the `NULL` ones skipped by `AsChangeset`.
+        fn restore_with_version<'conn>(
+            self,
+            expected_version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+    }
+
+    ///
+    /// Checks, that the SQL type of a soft delete column `C` is a nullable timestamp. The generated
+    /// statements name the column only through this trait, so a wrong column is reported by
+    /// [`check_soft_delete_column`], instead of by every statement using it.
+    ///
+    #[diagnostic::on_unimplemented(
+        message = "the soft delete column `{C}` of `{Self}` is no nullable timestamp",
+        label = "soft delete column of the wrong type",
+        note = "a soft delete column has to be a `Nullable` timestamp, which is set by `now`"
+    )]
+    pub trait SoftDeleteSqlType<C> {
+        /// The column itself.
+        type Column;
+    }
+
+    impl<C, ST> SoftDeleteSqlType<C> for diesel::sql_types::Nullable<ST>
+    where
+        diesel::dsl::now: diesel::expression::AsExpression<diesel::sql_types::Nullable<ST>>,
+        ST: diesel::sql_types::SqlType + diesel::sql_types::SingleValue,
+    {
+        type Column = C;
+    }
+
+    /// The soft delete column `C`, checked against its SQL type.
+    pub type SoftDeleteColumn<C> =
+        <<C as diesel::Expression>::SqlType as SoftDeleteSqlType<C>>::Column;
+
+    /// Static assertion of the soft delete column `C`, emitted by the derive macro.
+    pub const fn check_soft_delete_column<C>()
+    where
+        C: diesel::Column,
+        C::SqlType: SoftDeleteSqlType<C>,
+    {
+    }
+
+    /// Passes the soft delete column `C` to a statement, see [`SoftDeleteColumn`].
+    pub fn soft_delete_column<C>(column: C) -> SoftDeleteColumn<C>
+    where
+        C: diesel::Column,
+        C::SqlType: SoftDeleteSqlType<C, Column = C>,
+    {
+        column
+    }
+
+    ///
+    /// Statements of an entity with a soft delete column, implemented by the derive macro for a
+    /// reference to the entity. The statements of [`VersionedStatements`] skip deleted rows.
+    ///
+    pub trait SoftDeleteStatements<CONN, V> {
+        /// Marks the row of `expected_version` as deleted and sets its version to `version`.
+        fn delete_with_version(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        /// Clears the mark of the deleted row of `expected_version` and sets its version to
+        /// `version`.
+        fn undelete_with_version(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        /// Loads whether the row is marked as deleted, to tell a deleted row from a version conflict.
+        fn load_deleted(self, conn: &mut CONN) -> diesel::QueryResult<bool>;
+    }
+
+    ///
+    /// Async version of [`SoftDeleteStatements`].
+    ///
+    #[cfg(feature = "async")]
+    pub trait SoftDeleteStatementsAsync<'query, CONN: AsyncConnection, V> {
+        /// Marks the row of `expected_version` as deleted and sets its version to `version`.
+        fn delete_with_version<'conn>(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        /// Clears the mark of the deleted row of `expected_version` and sets its version to
+        /// `version`.
+        fn undelete_with_version<'conn>(
+            self,
+            expected_version: V,
+            version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        /// Loads whether the row is marked as deleted, to tell a deleted row from a version conflict.
+        fn load_deleted(
+            self,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<bool>> + Send;
+    }
+
+    ///
+    /// Statements of an entity with an outbox, implemented by the derive macro for a reference to
+    /// the entity.
+    ///
+    #[cfg(feature = "outbox")]
+    pub trait OutboxStatements<CONN, V> {
+        /// Inserts the event of the write `operation`, which resulted in `version`.
+        fn publish(
+            self,
+            operation: &'static str,
+            version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+    }
+
+    ///
+    /// Async version of [`OutboxStatements`].
+    ///
+    #[cfg(all(feature = "outbox", feature = "async"))]
+    pub trait OutboxStatementsAsync<'query, CONN: AsyncConnection, V> {
+        /// Inserts the event of the write `operation`, which resulted in `version`.
+        fn publish(
+            self,
+            operation: &'static str,
+            version: V,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<usize>> + Send;
+    }
+
+    ///
+    /// Statements of an entity versioned by the Postgres system column `xmin`. The updates return
+    /// the new `xmin`, or fail with `NotFound` if the expected `xmin` doesn't match.
+    ///
+    pub trait XminStatements<CONN, V> {
+        fn update_with_xmin(self, expected_version: V, conn: &mut CONN) -> diesel::QueryResult<V>;
+
+        fn delete_with_xmin(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> diesel::QueryResult<usize>;
+
+        fn touch_with_xmin(self, expected_version: V, conn: &mut CONN) -> diesel::QueryResult<V>;
+    }
+
+    ///
+    /// Async version of [`XminStatements`].
+    ///
+    #[cfg(feature = "async")]
+    pub trait XminStatementsAsync<'query, CONN: AsyncConnection, V> {
+        fn update_with_xmin(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<V>> + Send;
+
+        fn delete_with_xmin<'conn>(
+            self,
+            expected_version: V,
+            conn: &'conn mut CONN,
+        ) -> CONN::ExecuteFuture<'conn, 'query>;
+
+        fn touch_with_xmin(
+            self,
+            expected_version: V,
+            conn: &mut CONN,
+        ) -> impl Future<Output = diesel::QueryResult<V>> + Send;
+    }
 }
 
-pub use diesel_versioning_derives::Versioned;
 #[cfg(feature = "async")]
-pub use diesel_versioning_derives::VersionedAsync;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_async {
+    ($($tokens:tt)*) => {
+        $($tokens)*
+    };
+}
+
+#[cfg(not(feature = "async"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_async {
+    ($($tokens:tt)*) => {};
+}
+
+pub use diesel_versioning_derives::Versioned;
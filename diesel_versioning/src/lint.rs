@@ -0,0 +1,170 @@
+//! Audits a live database for tables, which are not prepared for optimistic locking.
+
+use std::fmt;
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use diesel::{
+    QueryResult, QueryableByName, RunQueryDsl,
+    connection::LoadConnection,
+    sql_query,
+    sql_types::{Bool, Nullable, Text},
+};
+
+/// Table of diesel_migrations, which is never versioned.
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+const MIGRATIONS_TABLE: &str = "__diesel_schema_migrations";
+
+///
+/// Problem of the version column of a table.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintIssue {
+    /// The table has no version column.
+    MissingColumn,
+    /// The version column is nullable, so rows may be unversioned.
+    NullableColumn,
+    /// The version column is `NOT NULL` without default, so every insert has to set the version.
+    MissingDefault,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::MissingColumn => f.write_str("missing version column"),
+            LintIssue::NullableColumn => f.write_str("nullable version column"),
+            LintIssue::MissingDefault => f.write_str("version column without default"),
+        }
+    }
+}
+
+///
+/// Table whose version column has an issue.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub table: String,
+    pub issue: LintIssue,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.table, self.issue)
+    }
+}
+
+///
+/// Lists the tables of a database, whose version column is missing, nullable or without default,
+/// so the adoption of optimistic locking can be audited. The table of diesel_migrations is
+/// skipped, as are the internal tables of SQLite.
+///
+/// Tables versioned by `#[version(xmin)]` have no version column, so they should be ignored.
+///
+/// ```ignore
+/// let findings = VersionLint::new("version").ignore("sessions").sqlite(conn)?;
+/// for finding in findings {
+///     println!("{finding}");
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct VersionLint<'a> {
+    column: &'a str,
+    ignored: Vec<&'a str>,
+}
+
+impl<'a> VersionLint<'a> {
+    ///
+    /// Checks the version `column` of all tables.
+    ///
+    pub fn new(column: &'a str) -> Self {
+        Self {
+            column,
+            ignored: Vec::new(),
+        }
+    }
+
+    ///
+    /// Skips `table`, e.g. a table which is never updated.
+    ///
+    pub fn ignore(mut self, table: &'a str) -> Self {
+        self.ignored.push(table);
+        self
+    }
+
+    ///
+    /// Audits the tables of a SQLite database.
+    ///
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite<CONN>(&self, conn: &mut CONN) -> QueryResult<Vec<LintFinding>>
+    where
+        CONN: LoadConnection<Backend = diesel::sqlite::Sqlite>,
+    {
+        let rows = sql_query(
+            "SELECT m.name AS table_name, p.\"notnull\" AS not_null, p.dflt_value AS default_value
+            FROM sqlite_master AS m LEFT JOIN pragma_table_info(m.name) AS p ON p.name = ?
+            WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+            ORDER BY m.name",
+        )
+        .bind::<Text, _>(self.column)
+        .load::<VersionColumnRow>(conn)?;
+        Ok(self.findings(rows))
+    }
+
+    ///
+    /// Audits the tables of the current schema of a Postgres database.
+    ///
+    #[cfg(feature = "postgres")]
+    pub fn postgres<CONN>(&self, conn: &mut CONN) -> QueryResult<Vec<LintFinding>>
+    where
+        CONN: LoadConnection<Backend = diesel::pg::Pg>,
+    {
+        let rows = sql_query(
+            "SELECT t.table_name::text AS table_name, c.is_nullable = 'NO' AS not_null,
+                c.column_default::text AS default_value
+            FROM information_schema.tables AS t LEFT JOIN information_schema.columns AS c
+                ON c.table_schema = t.table_schema AND c.table_name = t.table_name
+                AND c.column_name = $1
+            WHERE t.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+            ORDER BY t.table_name",
+        )
+        .bind::<Text, _>(self.column)
+        .load::<VersionColumnRow>(conn)?;
+        Ok(self.findings(rows))
+    }
+
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    fn findings(&self, rows: Vec<VersionColumnRow>) -> Vec<LintFinding> {
+        rows.into_iter()
+            .filter(|row| {
+                row.table_name != MIGRATIONS_TABLE
+                    && !self.ignored.iter().any(|table| *table == row.table_name)
+            })
+            .filter_map(|row| {
+                let issue = match (row.not_null, &row.default_value) {
+                    (None, _) => LintIssue::MissingColumn,
+                    (Some(false), _) => LintIssue::NullableColumn,
+                    (Some(true), None) => LintIssue::MissingDefault,
+                    (Some(true), Some(_)) => return None,
+                };
+                Some(LintFinding {
+                    table: row.table_name,
+                    issue,
+                })
+            })
+            .collect()
+    }
+}
+
+///
+/// Version column of a table, all `NULL` if the table has none.
+///
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+#[derive(QueryableByName)]
+struct VersionColumnRow {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+    #[diesel(sql_type = Nullable<Bool>)]
+    not_null: Option<bool>,
+    #[diesel(sql_type = Nullable<Text>)]
+    default_value: Option<String>,
+}
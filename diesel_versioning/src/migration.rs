@@ -0,0 +1,389 @@
+//! SQL for migrations, e.g. to enforce the version increment for writers bypassing diesel.
+//!
+//! The generated SQL is meant to be copied into the `up.sql` and `down.sql` of a diesel migration,
+//! to be executed by `batch_execute` or to be run as [`SqlMigration`] by the `MigrationHarness` of
+//! diesel_migrations.
+
+use std::fmt;
+
+use diesel::{
+    backend::Backend,
+    connection::BoxableConnection,
+    migration::{self, Migration, MigrationMetadata, MigrationName, MigrationVersion},
+};
+
+///
+/// Adds a version column to an existing table. Existing rows are backfilled with the initial
+/// version by the default of the column, which also versions rows inserted without a version.
+///
+/// ```
+/// use diesel_versioning::migration::AddVersionColumn;
+///
+/// let column = AddVersionColumn::new("users", "version").index();
+/// let up = column.postgres_up();
+/// let down = column.postgres_down();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct AddVersionColumn<'a> {
+    table: &'a str,
+    column: &'a str,
+    sql_type: &'a str,
+    initial: i64,
+    nullable: bool,
+    index: bool,
+}
+
+impl<'a> AddVersionColumn<'a> {
+    ///
+    /// Adds the `INTEGER` column `column` to `table`, starting with version 1.
+    ///
+    pub fn new(table: &'a str, column: &'a str) -> Self {
+        Self {
+            table,
+            column,
+            sql_type: "INTEGER",
+            initial: 1,
+            nullable: false,
+            index: false,
+        }
+    }
+
+    ///
+    /// SQL type of the column, e.g. `SMALLINT` or `BIGINT` for an `i16` or `i64` version.
+    ///
+    pub fn sql_type(mut self, sql_type: &'a str) -> Self {
+        self.sql_type = sql_type;
+        self
+    }
+
+    ///
+    /// Version of the existing rows.
+    ///
+    pub fn initial(mut self, initial: i64) -> Self {
+        self.initial = initial;
+        self
+    }
+
+    ///
+    /// Adds a nullable column without default instead, so the existing rows stay unversioned until
+    /// their first update, see `#[version(initial = ...)]`.
+    ///
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    ///
+    /// Additionally creates an index on the column.
+    ///
+    pub fn index(mut self) -> Self {
+        self.index = true;
+        self
+    }
+
+    /// Name of the index on the column.
+    fn index_name(&self) -> String {
+        format!("{}_{}_idx", self.table, self.column)
+    }
+
+    fn up(&self, quote: fn(&str) -> String) -> String {
+        let table = quote(self.table);
+        let column = quote(self.column);
+        let sql_type = self.sql_type;
+        let constraint = if self.nullable {
+            "NULL".to_owned()
+        } else {
+            format!("NOT NULL DEFAULT {}", self.initial)
+        };
+        let mut sql = format!("ALTER TABLE {table} ADD COLUMN {column} {sql_type} {constraint};\n");
+        if self.index {
+            let index = quote(&self.index_name());
+            sql.push_str(&format!("CREATE INDEX {index} ON {table} ({column});\n"));
+        }
+        sql
+    }
+
+    fn down(&self, quote: fn(&str) -> String, drop_index: bool) -> String {
+        let mut sql = String::new();
+        if self.index && drop_index {
+            sql.push_str(&format!("DROP INDEX {};\n", quote(&self.index_name())));
+        }
+        sql.push_str(&format!(
+            "ALTER TABLE {} DROP COLUMN {};\n",
+            quote(self.table),
+            quote(self.column)
+        ));
+        sql
+    }
+
+    ///
+    /// Adds the column on Postgres.
+    ///
+    pub fn postgres_up(&self) -> String {
+        self.up(quote_identifier)
+    }
+
+    ///
+    /// Drops the column added by [`AddVersionColumn::postgres_up`], together with its index.
+    ///
+    pub fn postgres_down(&self) -> String {
+        self.down(quote_identifier, false)
+    }
+
+    ///
+    /// Adds the column on SQLite.
+    ///
+    pub fn sqlite_up(&self) -> String {
+        self.up(quote_identifier)
+    }
+
+    ///
+    /// Drops the column added by [`AddVersionColumn::sqlite_up`]. SQLite can't drop an indexed
+    /// column, so the index is dropped before.
+    ///
+    pub fn sqlite_down(&self) -> String {
+        self.down(quote_identifier, true)
+    }
+
+    ///
+    /// Adds the column on MySQL.
+    ///
+    pub fn mysql_up(&self) -> String {
+        self.up(quote_mysql_identifier)
+    }
+
+    ///
+    /// Drops the column added by [`AddVersionColumn::mysql_up`], together with its index.
+    ///
+    pub fn mysql_down(&self) -> String {
+        self.down(quote_mysql_identifier, false)
+    }
+}
+
+///
+/// Migration executing generated SQL, so it can be run by the `MigrationHarness` of
+/// diesel_migrations like an embedded migration and is recorded as applied under its version.
+///
+/// ```ignore
+/// let column = AddVersionColumn::new("users", "version");
+/// let migration = SqlMigration::new("2026-10-18-130000", column.postgres_up(), column.postgres_down());
+/// conn.run_migration(&migration)?;
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SqlMigration {
+    version: String,
+    up: String,
+    down: String,
+}
+
+impl SqlMigration {
+    ///
+    /// Migration recorded under `version`, running `up` and reverted by `down`.
+    ///
+    pub fn new(version: impl Into<String>, up: impl Into<String>, down: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            up: up.into(),
+            down: down.into(),
+        }
+    }
+}
+
+impl<DB: Backend> Migration<DB> for SqlMigration {
+    fn run(&self, conn: &mut dyn BoxableConnection<DB>) -> migration::Result<()> {
+        conn.batch_execute(&self.up)?;
+        Ok(())
+    }
+
+    fn revert(&self, conn: &mut dyn BoxableConnection<DB>) -> migration::Result<()> {
+        conn.batch_execute(&self.down)?;
+        Ok(())
+    }
+
+    fn metadata(&self) -> &dyn MigrationMetadata {
+        self
+    }
+
+    fn name(&self) -> &dyn MigrationName {
+        self
+    }
+}
+
+impl MigrationMetadata for SqlMigration {}
+
+impl MigrationName for SqlMigration {
+    fn version(&self) -> MigrationVersion<'_> {
+        MigrationVersion::from(&*self.version)
+    }
+}
+
+impl fmt::Display for SqlMigration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.version)
+    }
+}
+
+///
+/// Trigger enforcing, that every update of a table increments the version by 1, also for writers
+/// bypassing diesel like psql or other services. A nullable version may change from `NULL` to any
+/// initial version.
+///
+/// By default an update not incrementing the version is rejected. With [`VersionTrigger::increment`]
+/// an update keeping the version increments it instead, so writers not knowing about the version
+/// still invalidate the copies of others.
+///
+/// The SQLite trigger of [`VersionTrigger::increment`] updates the row by its `rowid`, so it is not
+/// supported for tables `WITHOUT ROWID`.
+///
+/// ```
+/// use diesel_versioning::migration::VersionTrigger;
+///
+/// let trigger = VersionTrigger::new("users", "version").increment();
+/// let up = trigger.postgres_up();
+/// let down = trigger.postgres_down();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct VersionTrigger<'a> {
+    table: &'a str,
+    column: &'a str,
+    increment: bool,
+}
+
+impl<'a> VersionTrigger<'a> {
+    ///
+    /// Trigger rejecting updates of `table`, which don't increment the version `column`.
+    ///
+    pub fn new(table: &'a str, column: &'a str) -> Self {
+        Self {
+            table,
+            column,
+            increment: false,
+        }
+    }
+
+    ///
+    /// Increments the version of updates keeping it, instead of rejecting them.
+    ///
+    pub fn increment(mut self) -> Self {
+        self.increment = true;
+        self
+    }
+
+    /// Name of the check trigger, and of its function for Postgres.
+    fn check_name(&self) -> String {
+        quote_identifier(&format!("{}_{}_check", self.table, self.column))
+    }
+
+    /// Name of the SQLite trigger incrementing a kept version.
+    fn increment_name(&self) -> String {
+        quote_identifier(&format!("{}_{}_increment", self.table, self.column))
+    }
+
+    ///
+    /// Creates the trigger for Postgres. A rejected update fails as check violation.
+    ///
+    pub fn postgres_up(&self) -> String {
+        let name = self.check_name();
+        let table = quote_identifier(self.table);
+        let column = quote_identifier(self.column);
+        let increment = if self.increment {
+            format!(
+                "    IF NEW.{column} IS NOT DISTINCT FROM OLD.{column} THEN
+        NEW.{column} := OLD.{column} + 1;
+    END IF;
+"
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "CREATE FUNCTION {name}() RETURNS trigger AS $$
+BEGIN
+{increment}    IF NEW.{column} IS DISTINCT FROM OLD.{column} + 1
+        AND NOT (OLD.{column} IS NULL AND NEW.{column} IS NOT NULL) THEN
+        RAISE EXCEPTION 'version of % has to be incremented by 1', TG_TABLE_NAME
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER {name} BEFORE UPDATE ON {table}
+FOR EACH ROW EXECUTE FUNCTION {name}();
+"
+        )
+    }
+
+    ///
+    /// Drops the trigger created by [`VersionTrigger::postgres_up`].
+    ///
+    pub fn postgres_down(&self) -> String {
+        let name = self.check_name();
+        let table = quote_identifier(self.table);
+        format!("DROP TRIGGER {name} ON {table};\nDROP FUNCTION {name}();\n")
+    }
+
+    ///
+    /// Creates the triggers for SQLite. A rejected update is aborted by `RAISE(ABORT, ...)`.
+    ///
+    pub fn sqlite_up(&self) -> String {
+        let name = self.check_name();
+        let table = quote_identifier(self.table);
+        let column = quote_identifier(self.column);
+        let message = format!("version of {} has to be incremented by 1", self.table);
+        let message = message.replace('\'', "''");
+        // a kept version is incremented by the second trigger
+        let kept = if self.increment {
+            format!("\n    AND NEW.{column} IS NOT OLD.{column}")
+        } else {
+            String::new()
+        };
+        let mut sql = format!(
+            "CREATE TRIGGER {name} BEFORE UPDATE ON {table}
+FOR EACH ROW WHEN NEW.{column} IS NOT OLD.{column} + 1
+    AND NOT (OLD.{column} IS NULL AND NEW.{column} IS NOT NULL){kept}
+BEGIN
+    SELECT RAISE(ABORT, '{message}');
+END;
+"
+        );
+        if self.increment {
+            // SQLite can't change NEW within a trigger, so the row is updated afterwards
+            let name = self.increment_name();
+            sql.push_str(&format!(
+                "
+CREATE TRIGGER {name} AFTER UPDATE ON {table}
+FOR EACH ROW WHEN NEW.{column} IS OLD.{column}
+BEGIN
+    UPDATE {table} SET {column} = OLD.{column} + 1 WHERE rowid = NEW.rowid;
+END;
+"
+            ));
+        }
+        sql
+    }
+
+    ///
+    /// Drops the triggers created by [`VersionTrigger::sqlite_up`].
+    ///
+    pub fn sqlite_down(&self) -> String {
+        let mut sql = format!("DROP TRIGGER {};\n", self.check_name());
+        if self.increment {
+            sql.push_str(&format!("DROP TRIGGER {};\n", self.increment_name()));
+        }
+        sql
+    }
+}
+
+/// Quotes an identifier for Postgres and SQLite.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Quotes an identifier for MySQL.
+fn quote_mysql_identifier(identifier: &str) -> String {
+    format!("`{}`", identifier.replace('`', "``"))
+}
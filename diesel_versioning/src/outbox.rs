@@ -0,0 +1,59 @@
+//! Events of versioned writes, inserted into an outbox table within the transaction of the write.
+
+use diesel::{
+    Column, QueryResult,
+    result::Error,
+    sql_types::{BigInt, Text},
+};
+use serde::Serialize;
+
+///
+/// Version of an entity, written as `BigInt` into the outbox.
+///
+#[doc(hidden)]
+pub trait EventVersion {
+    fn event_version(self) -> i64;
+}
+
+impl EventVersion for i16 {
+    fn event_version(self) -> i64 {
+        self.into()
+    }
+}
+
+impl EventVersion for i32 {
+    fn event_version(self) -> i64 {
+        self.into()
+    }
+}
+
+impl EventVersion for i64 {
+    fn event_version(self) -> i64 {
+        self
+    }
+}
+
+/// A nullable version is always set by a write, so `None` isn't published.
+impl<T: EventVersion> EventVersion for Option<T> {
+    fn event_version(self) -> i64 {
+        self.map_or(0, EventVersion::event_version)
+    }
+}
+
+/// Serializes the id or payload of an event as JSON.
+#[doc(hidden)]
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> QueryResult<String> {
+    serde_json::to_string(value).map_err(|err| Error::SerializationError(Box::new(err)))
+}
+
+/// Static assertion, that the outbox table has the columns of an event, emitted by the derive macro.
+#[doc(hidden)]
+pub const fn check_outbox_table<EntityType, EntityId, Version, Operation, Payload>()
+where
+    EntityType: Column<SqlType = Text>,
+    EntityId: Column<SqlType = Text>,
+    Version: Column<SqlType = BigInt>,
+    Operation: Column<SqlType = Text>,
+    Payload: Column<SqlType = Text>,
+{
+}
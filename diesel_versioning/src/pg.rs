@@ -0,0 +1,55 @@
+//! Postgres specific mappings, e.g. of the system column `xmin` used by `#[version(xmin)]`.
+
+use std::io::Write;
+
+use diesel::{
+    AsExpression, FromSqlRow,
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+};
+
+use self::sql_types::Xid;
+
+pub mod sql_types {
+    //! SQL types of Postgres, which are not provided by diesel.
+
+    ///
+    /// The Postgres type `xid`, a transaction id like the one of the system column `xmin`. It is
+    /// mapped to [`TransactionId`](super::TransactionId).
+    ///
+    #[derive(
+        Debug, Clone, Copy, Default, diesel::sql_types::SqlType, diesel::query_builder::QueryId,
+    )]
+    #[diesel(postgres_type(oid = 28, array_oid = 1011))]
+    pub struct Xid;
+}
+
+///
+/// Value of the Postgres type `xid`, e.g. of the system column `xmin`. A plain `u32` can't be used,
+/// as it can't be bound as `xid` due to the blanket impls of diesel.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Xid)]
+pub struct TransactionId(pub u32);
+
+impl From<TransactionId> for u32 {
+    fn from(value: TransactionId) -> Self {
+        value.0
+    }
+}
+
+impl FromSql<Xid, Pg> for TransactionId {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let bytes = <[u8; 4]>::try_from(value.as_bytes())
+            .map_err(|_| "invalid size of a xid, expected 4 bytes")?;
+        Ok(TransactionId(u32::from_be_bytes(bytes)))
+    }
+}
+
+impl ToSql<Xid, Pg> for TransactionId {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(&self.0.to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
@@ -0,0 +1,51 @@
+//! Soft delete of versioned entities, which keeps deleted rows as versioned tombstones.
+
+use diesel::{Connection, backend::Backend, result::Error};
+#[cfg(feature = "async")]
+use diesel_async::AsyncConnection;
+
+use crate::Versioned;
+#[cfg(feature = "async")]
+use crate::VersionedAsync;
+
+///
+/// Restores a soft deleted entity with `#[version(soft_delete = ...)]`. Implemented by the derive
+/// macro.
+///
+/// `delete_versioned` of such an entity sets the soft delete column to the current time and
+/// increments the version, instead of deleting the row. Updates of a deleted row fail with
+/// [`Conflict::Deleted`](crate::Conflict::Deleted).
+///
+/// Unlike `undelete_versioned`, `delete_versioned` doesn't set a field of the soft delete column, as
+/// the time is taken by the database. The field stays `None`, until the entity is loaded again.
+///
+pub trait SoftDelete<CONN, DB>: Versioned<CONN, DB>
+where
+    CONN: Connection<Backend = DB>,
+    DB: Backend,
+{
+    ///
+    /// Clears the soft delete column of the deleted entity. The version field will be checked and
+    /// incremented, a field of the soft delete column is set to `None`.
+    ///
+    fn undelete_versioned(&mut self, conn: &mut CONN) -> Result<(), Error>;
+}
+
+#[cfg(feature = "async")]
+///
+/// Async version of [`SoftDelete`].
+///
+pub trait SoftDeleteAsync<CONN, DB>: VersionedAsync<CONN, DB>
+where
+    CONN: AsyncConnection<Backend = DB>,
+    DB: Backend,
+{
+    ///
+    /// Clears the soft delete column of the deleted entity. The version field will be checked and
+    /// incremented, a field of the soft delete column is set to `None`.
+    ///
+    fn undelete_versioned(
+        &mut self,
+        conn: &mut CONN,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
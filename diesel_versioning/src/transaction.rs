@@ -0,0 +1,90 @@
+//! Writes of versioned entities accompanied by further statements, like the copy into the history
+//! table or the event of the outbox.
+
+use diesel::{Connection, QueryResult, result::Error};
+#[cfg(feature = "async")]
+use diesel_async::{AsyncConnection, scoped_futures::ScopedBoxFuture};
+
+///
+/// Runs `copy`, the write of the new version by `write` and `publish` within one transaction. The
+/// transaction is rolled back if not exactly one row was written, `publish` is only run for a
+/// written row. Returns the written rows.
+///
+#[doc(hidden)]
+pub fn write_in_transaction<CONN, COPY, WRITE, PUBLISH>(
+    conn: &mut CONN,
+    copy: COPY,
+    write: WRITE,
+    publish: PUBLISH,
+) -> QueryResult<usize>
+where
+    CONN: Connection,
+    COPY: FnOnce(&mut CONN) -> QueryResult<usize>,
+    WRITE: FnOnce(&mut CONN) -> QueryResult<usize>,
+    PUBLISH: FnOnce(&mut CONN) -> QueryResult<usize>,
+{
+    let mut written_rows = 0;
+    let result = conn.transaction(|conn| {
+        copy(conn)?;
+        written_rows = write(conn)?;
+        if written_rows == 1 {
+            publish(conn)?;
+            Ok(())
+        } else {
+            Err(Error::RollbackTransaction)
+        }
+    });
+    match result {
+        Ok(()) | Err(Error::RollbackTransaction) => Ok(written_rows),
+        Err(err) => Err(err),
+    }
+}
+
+///
+/// Async version of [`write_in_transaction`]. The statements are boxed, so a statement which is
+/// skipped can be passed as ready future.
+///
+#[cfg(feature = "async")]
+#[doc(hidden)]
+pub async fn write_in_transaction_async<'query, CONN, COPY, WRITE, PUBLISH>(
+    conn: &mut CONN,
+    copy: COPY,
+    write: WRITE,
+    publish: PUBLISH,
+) -> QueryResult<usize>
+where
+    CONN: AsyncConnection + Send,
+    COPY: for<'conn> FnOnce(&'conn mut CONN) -> ScopedBoxFuture<'query, 'conn, QueryResult<usize>>
+        + Send
+        + 'query,
+    WRITE: for<'conn> FnOnce(&'conn mut CONN) -> ScopedBoxFuture<'query, 'conn, QueryResult<usize>>
+        + Send
+        + 'query,
+    PUBLISH: for<'conn> FnOnce(&'conn mut CONN) -> ScopedBoxFuture<'query, 'conn, QueryResult<usize>>
+        + Send
+        + 'query,
+{
+    use diesel_async::scoped_futures::ScopedFutureExt;
+
+    let mut written_rows = 0;
+    let written = &mut written_rows;
+    let result = conn
+        .transaction(|conn| {
+            async move {
+                copy(conn).await?;
+                *written = write(conn).await?;
+                if *written == 1 {
+                    publish(conn).await?;
+                    Ok(())
+                } else {
+                    Err(Error::RollbackTransaction)
+                }
+            }
+            .scope_boxed()
+        })
+        .await;
+    match result {
+        Ok(()) | Err(Error::RollbackTransaction) => Ok(written_rows),
+        Err(err) => Err(err),
+    }
+}
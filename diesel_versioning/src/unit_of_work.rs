@@ -0,0 +1,521 @@
+//! Flushes many versioned entities within one transaction.
+
+use std::fmt;
+
+use diesel::{
+    Connection, Insertable, Table, query_builder::InsertStatement, query_dsl::methods::ExecuteDsl,
+    result::Error,
+};
+#[cfg(feature = "async")]
+use diesel_async::AsyncConnection;
+#[cfg(feature = "async")]
+use diesel_async::scoped_futures::ScopedFutureExt;
+
+use crate::Versioned;
+#[cfg(feature = "async")]
+use crate::{VersionedAsync, VersionedFuture};
+
+///
+/// Kind of a registered operation.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperationKind::Insert => f.write_str("insert"),
+            OperationKind::Update => f.write_str("update"),
+            OperationKind::Delete => f.write_str("delete"),
+        }
+    }
+}
+
+///
+/// Operation of a unit of work which failed.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedOperation {
+    kind: OperationKind,
+    index: usize,
+    type_name: &'static str,
+}
+
+impl FailedOperation {
+    /// Kind of the operation.
+    pub fn kind(&self) -> OperationKind {
+        self.kind
+    }
+
+    /// Index of the entity in the order of registration.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Type name of the entity.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+///
+/// Error of [`UnitOfWork::commit`]. All changes were rolled back.
+///
+#[derive(Debug)]
+pub struct UnitOfWorkError {
+    operation: Option<FailedOperation>,
+    error: Error,
+}
+
+impl UnitOfWorkError {
+    /// The operation which failed, `None` if the transaction itself failed.
+    pub fn operation(&self) -> Option<&FailedOperation> {
+        self.operation.as_ref()
+    }
+
+    /// The error of the database or the version conflict.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the error of the database or the version conflict.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl From<Error> for UnitOfWorkError {
+    fn from(error: Error) -> Self {
+        Self {
+            operation: None,
+            error,
+        }
+    }
+}
+
+impl fmt::Display for UnitOfWorkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.operation {
+            Some(operation) => write!(
+                f,
+                "{} of {} (#{}) failed: {}",
+                operation.kind, operation.type_name, operation.index, self.error
+            ),
+            None => write!(f, "unit of work failed: {}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for UnitOfWorkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A registered entity with its operation.
+struct Registered<P> {
+    kind: OperationKind,
+    index: usize,
+    type_name: &'static str,
+    pending: P,
+}
+
+impl<P> Registered<P> {
+    fn failed(&self, error: Error) -> UnitOfWorkError {
+        UnitOfWorkError {
+            operation: Some(FailedOperation {
+                kind: self.kind,
+                index: self.index,
+                type_name: self.type_name,
+            }),
+            error,
+        }
+    }
+}
+
+/// Registers the entity and keeps the operations ordered by inserts, updates and deletes.
+fn register<P>(
+    registered: &mut Vec<Registered<P>>,
+    kind: OperationKind,
+    type_name: &'static str,
+    pending: P,
+) {
+    let index = registered.len();
+    let position = registered.partition_point(|other| other.kind as u8 <= kind as u8);
+    registered.insert(
+        position,
+        Registered {
+            kind,
+            index,
+            type_name,
+            pending,
+        },
+    );
+}
+
+/// Operation on a registered entity.
+trait Pending<CONN> {
+    fn execute(&mut self, conn: &mut CONN) -> Result<(), Error>;
+
+    /// Restores the entity, if the transaction was rolled back.
+    fn rollback(&mut self) {}
+}
+
+/// Records to insert into the table, taken by the first execution.
+struct Insert<U, TABLE>(Option<(TABLE, U)>);
+
+impl<U, TABLE, CONN> Pending<CONN> for Insert<U, TABLE>
+where
+    CONN: Connection,
+    TABLE: Table,
+    U: Insertable<TABLE>,
+    InsertStatement<TABLE, U::Values>: ExecuteDsl<CONN>,
+{
+    fn execute(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        match self.0.take() {
+            Some((table, records)) => {
+                ExecuteDsl::execute(records.insert_into(table), conn).map(|_| ())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+struct Update<'a, T> {
+    entity: &'a mut T,
+    snapshot: Option<T>,
+}
+
+impl<T, CONN> Pending<CONN> for Update<'_, T>
+where
+    CONN: Connection,
+    T: Versioned<CONN, CONN::Backend> + Clone,
+{
+    fn execute(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.snapshot = Some(self.entity.clone());
+        self.entity.update_versioned(conn)
+    }
+
+    fn rollback(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.entity = snapshot;
+        }
+    }
+}
+
+/// A soft delete changes the version of the entity, so it is restored like by [`Update`].
+struct Delete<'a, T> {
+    entity: &'a mut T,
+    snapshot: Option<T>,
+}
+
+impl<T, CONN> Pending<CONN> for Delete<'_, T>
+where
+    CONN: Connection,
+    T: Versioned<CONN, CONN::Backend> + Clone,
+{
+    fn execute(&mut self, conn: &mut CONN) -> Result<(), Error> {
+        self.snapshot = Some(self.entity.clone());
+        self.entity.delete_versioned(conn)
+    }
+
+    fn rollback(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.entity = snapshot;
+        }
+    }
+}
+
+///
+/// Collects new, dirty and deleted entities and writes them within one transaction by
+/// [`UnitOfWork::commit`]. Inserts are executed first, then updates and deletes, each in the order
+/// of registration. The first failure, e.g. a version conflict, rolls back the transaction.
+///
+pub struct UnitOfWork<'a, CONN> {
+    registered: Vec<Registered<Box<dyn Pending<CONN> + 'a>>>,
+}
+
+impl<'a, CONN> UnitOfWork<'a, CONN>
+where
+    CONN: Connection + 'a,
+{
+    pub fn new() -> Self {
+        Self {
+            registered: Vec::new(),
+        }
+    }
+
+    ///
+    /// Registers new records, e.g. a reference to a new entity, which will be inserted into
+    /// `table` like `diesel::insert_into(table).values(records)`.
+    ///
+    pub fn register_new<U, TABLE>(&mut self, table: TABLE, records: U)
+    where
+        TABLE: Table + 'a,
+        U: Insertable<TABLE> + 'a,
+        InsertStatement<TABLE, U::Values>: ExecuteDsl<CONN>,
+    {
+        let pending = Box::new(Insert(Some((table, records))));
+        register(
+            &mut self.registered,
+            OperationKind::Insert,
+            std::any::type_name::<U>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Registers a changed entity, which will be updated by [`Versioned::update_versioned`]. If the
+    /// transaction is rolled back, the entity is restored.
+    ///
+    pub fn register_dirty<T>(&mut self, entity: &'a mut T)
+    where
+        T: Versioned<CONN, CONN::Backend> + Clone,
+    {
+        let pending = Box::new(Update {
+            entity,
+            snapshot: None,
+        });
+        register(
+            &mut self.registered,
+            OperationKind::Update,
+            std::any::type_name::<T>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Registers an entity, which will be deleted by [`Versioned::delete_versioned`]. If the
+    /// transaction is rolled back, the entity is restored.
+    ///
+    pub fn register_deleted<T>(&mut self, entity: &'a mut T)
+    where
+        T: Versioned<CONN, CONN::Backend> + Clone,
+    {
+        let pending = Box::new(Delete {
+            entity,
+            snapshot: None,
+        });
+        register(
+            &mut self.registered,
+            OperationKind::Delete,
+            std::any::type_name::<T>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Executes all registered operations within one transaction.
+    ///
+    pub fn commit(mut self, conn: &mut CONN) -> Result<(), UnitOfWorkError> {
+        let registered = &mut self.registered;
+        let result = conn.transaction(|conn| {
+            for registered in registered.iter_mut() {
+                registered
+                    .pending
+                    .execute(conn)
+                    .map_err(|error| registered.failed(error))?;
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            for registered in &mut self.registered {
+                registered.pending.rollback();
+            }
+        }
+        result
+    }
+}
+
+impl<'a, CONN> Default for UnitOfWork<'a, CONN>
+where
+    CONN: Connection + 'a,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Async operation on a registered entity.
+#[cfg(feature = "async")]
+trait PendingAsync<CONN>: Send {
+    fn execute<'b>(&'b mut self, conn: &'b mut CONN) -> VersionedFuture<'b>;
+
+    /// Restores the entity, if the transaction was rolled back.
+    fn rollback(&mut self) {}
+}
+
+#[cfg(feature = "async")]
+impl<U, TABLE, CONN> PendingAsync<CONN> for Insert<U, TABLE>
+where
+    CONN: AsyncConnection,
+    TABLE: Table + Send,
+    U: Insertable<TABLE> + Send,
+    InsertStatement<TABLE, U::Values>: diesel_async::methods::ExecuteDsl<CONN>,
+{
+    fn execute<'b>(&'b mut self, conn: &'b mut CONN) -> VersionedFuture<'b> {
+        match self.0.take() {
+            Some((table, records)) => {
+                let future =
+                    diesel_async::methods::ExecuteDsl::execute(records.insert_into(table), conn);
+                Box::pin(async move { future.await.map(|_| ()) })
+            }
+            None => Box::pin(async { Ok(()) }),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, CONN> PendingAsync<CONN> for Update<'_, T>
+where
+    CONN: AsyncConnection,
+    T: VersionedAsync<CONN, CONN::Backend> + Clone + Send,
+{
+    fn execute<'b>(&'b mut self, conn: &'b mut CONN) -> VersionedFuture<'b> {
+        self.snapshot = Some(self.entity.clone());
+        Box::pin(self.entity.update_versioned(conn))
+    }
+
+    fn rollback(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.entity = snapshot;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, CONN> PendingAsync<CONN> for Delete<'_, T>
+where
+    CONN: AsyncConnection,
+    T: VersionedAsync<CONN, CONN::Backend> + Clone + Send,
+{
+    fn execute<'b>(&'b mut self, conn: &'b mut CONN) -> VersionedFuture<'b> {
+        self.snapshot = Some(self.entity.clone());
+        Box::pin(self.entity.delete_versioned(conn))
+    }
+
+    fn rollback(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.entity = snapshot;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+///
+/// Async version of [`UnitOfWork`], using [`AsyncConnection::transaction`].
+///
+pub struct UnitOfWorkAsync<'a, CONN> {
+    registered: Vec<Registered<Box<dyn PendingAsync<CONN> + 'a>>>,
+}
+
+#[cfg(feature = "async")]
+impl<'a, CONN> UnitOfWorkAsync<'a, CONN>
+where
+    CONN: AsyncConnection + 'a,
+{
+    pub fn new() -> Self {
+        Self {
+            registered: Vec::new(),
+        }
+    }
+
+    ///
+    /// Registers new records, e.g. a reference to a new entity, which will be inserted into
+    /// `table` like `diesel::insert_into(table).values(records)`.
+    ///
+    pub fn register_new<U, TABLE>(&mut self, table: TABLE, records: U)
+    where
+        TABLE: Table + Send + 'a,
+        U: Insertable<TABLE> + Send + 'a,
+        InsertStatement<TABLE, U::Values>: diesel_async::methods::ExecuteDsl<CONN>,
+    {
+        let pending = Box::new(Insert(Some((table, records))));
+        register(
+            &mut self.registered,
+            OperationKind::Insert,
+            std::any::type_name::<U>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Registers a changed entity, which will be updated by [`VersionedAsync::update_versioned`].
+    /// If the transaction is rolled back, the entity is restored.
+    ///
+    pub fn register_dirty<T>(&mut self, entity: &'a mut T)
+    where
+        T: VersionedAsync<CONN, CONN::Backend> + Clone + Send,
+    {
+        let pending = Box::new(Update {
+            entity,
+            snapshot: None,
+        });
+        register(
+            &mut self.registered,
+            OperationKind::Update,
+            std::any::type_name::<T>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Registers an entity, which will be deleted by [`VersionedAsync::delete_versioned`]. If the
+    /// transaction is rolled back, the entity is restored.
+    ///
+    pub fn register_deleted<T>(&mut self, entity: &'a mut T)
+    where
+        T: VersionedAsync<CONN, CONN::Backend> + Clone + Send,
+    {
+        let pending = Box::new(Delete {
+            entity,
+            snapshot: None,
+        });
+        register(
+            &mut self.registered,
+            OperationKind::Delete,
+            std::any::type_name::<T>(),
+            pending,
+        );
+    }
+
+    ///
+    /// Executes all registered operations within one transaction.
+    ///
+    pub async fn commit(mut self, conn: &mut CONN) -> Result<(), UnitOfWorkError> {
+        let registered = &mut self.registered;
+        let result = conn
+            .transaction(|conn| {
+                async move {
+                    for registered in registered.iter_mut() {
+                        if let Err(error) = registered.pending.execute(conn).await {
+                            return Err(registered.failed(error));
+                        }
+                    }
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await;
+        if result.is_err() {
+            for registered in &mut self.registered {
+                registered.pending.rollback();
+            }
+        }
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, CONN> Default for UnitOfWorkAsync<'a, CONN>
+where
+    CONN: AsyncConnection + 'a,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,515 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{DeriveInput, Path, parse_quote};
+
+use crate::model::Model;
+use crate::versioned::{
+    diesel_async_path, diesel_path, filtered_execute, impl_generics, match_expected_version,
+    soft_delete_filter, version_bind, version_bind_type, version_column_expression,
+    version_filters, versioned_write,
+};
+
+///
+/// Static assertions, that every column of the entity exists in the history table with the same
+/// SQL type. Spanned on the fields, like the check of the version column.
+///
+pub(crate) fn check_history_columns(model: &Model, history: &Path) -> TokenStream {
+    let krate = model.krate();
+    let table_name = model.table_name();
+    let checks = model.columns().iter().map(|column| {
+        let column_name = column.column_name();
+        quote_spanned! {column_name.span()=>
+            const _: () = #krate::__private::check_history_column::<#table_name::#column_name, #history::#column_name>();
+        }
+    });
+    quote!(#(#checks)*)
+}
+
+///
+/// Query of `table` filtered by the primary key of the entity and `filters`, as type and as
+/// expression.
+///
+fn filtered(
+    model: &Model,
+    table: &Path,
+    filters: &[(TokenStream, TokenStream)],
+) -> (TokenStream, TokenStream) {
+    let diesel = diesel_path(model);
+    let key_filters = model.primary_key().map(|column| {
+        let column_name = column.column_name();
+        let member = column.member();
+        let ty = column.ty();
+        (
+            quote!(#diesel::dsl::Eq<#table::#column_name, &'query #ty>),
+            quote!(#diesel::ExpressionMethods::eq(#table::#column_name, &self.#member)),
+        )
+    });
+    let mut query_type = quote!(#table::table);
+    let mut query = quote!(#table::table);
+    for (filter_type, filter) in key_filters.chain(filters.iter().cloned()) {
+        query_type = quote!(#diesel::dsl::Filter<#query_type, #filter_type>);
+        query = quote!(#diesel::QueryDsl::filter(#query, #filter));
+    }
+    (query_type, query)
+}
+
+///
+/// Selects all columns of `table` in the order of the fields, so the entity can be loaded by its
+/// `Queryable` impl.
+///
+fn selected(
+    model: &Model,
+    table: &Path,
+    (query_type, query): (TokenStream, TokenStream),
+) -> (TokenStream, TokenStream) {
+    let diesel = diesel_path(model);
+    let columns = model.columns().iter().map(|column| {
+        let column_name = column.column_name();
+        quote!(#table::#column_name)
+    });
+    let columns = quote!((#(#columns,)*));
+    (
+        quote!(#diesel::dsl::Select<#query_type, #columns>),
+        quote!(#diesel::QueryDsl::select(#query, #columns)),
+    )
+}
+
+///
+/// Implements the statements of the history for a reference to the entity, see
+/// `derive_statements` of an integer version.
+///
+pub(crate) fn derive_statements(
+    item: &DeriveInput,
+    model: &Model,
+    impl_async: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    let table_name = model.table_name();
+    let history = model
+        .version_field()
+        .history()
+        .expect("statements are only derived with a history");
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = item.generics.clone();
+    generics.params.insert(0, parse_quote!('query));
+    generics.params.push(parse_quote!(CONN));
+
+    // the row image of the expected version
+    let filters = version_filters(model);
+    let column_names = model.columns().iter().map(|column| {
+        let column_name = column.column_name();
+        quote!(<#history::#column_name as #diesel::Column>::NAME)
+    });
+    let column_names = quote!(&[#(#column_names,)*]);
+    let copies: Vec<_> = filters
+        .iter()
+        .cloned()
+        .map(|filter| {
+            let (select_type, select) =
+                selected(model, table_name, filtered(model, table_name, &[filter]));
+            (
+                quote!(#krate::__private::CopyToHistory<#history::table, #select_type>),
+                quote!(#krate::__private::CopyToHistory::new(#history::table, COLUMNS, #select)),
+            )
+        })
+        .collect();
+
+    // a version of the history table or the current one
+    let version_query = |table: &Path| {
+        let bind_type = version_bind_type(model, table);
+        let column = version_column_expression(model, table);
+        let bind = version_bind(model, table, quote!(version));
+        selected(
+            model,
+            table,
+            filtered(
+                model,
+                table,
+                &[(
+                    quote!(#diesel::dsl::Eq<#table::#version_column, #bind_type>),
+                    quote!(#diesel::ExpressionMethods::eq(#column, #bind)),
+                )],
+            ),
+        )
+    };
+    let (history_type, history_query) = version_query(history);
+    let (current_type, current_query) = version_query(table_name);
+    let (all_type, all_query) = selected(model, history, filtered(model, history, &[]));
+    let all_type = quote!(#diesel::dsl::Order<#all_type, #history::#version_column>);
+    let history_version = version_column_expression(model, history);
+    let all_query = quote!(#diesel::QueryDsl::order(#all_query, #history_version));
+
+    // the restored image is written column by column, as AsChangeset skips the fields of `None`
+    let restored_columns = model.columns().iter().filter(|column| {
+        column.column_name() != version_column
+            && model
+                .primary_key()
+                .all(|key| key.column_name() != column.column_name())
+    });
+    let mut restore_types = Vec::new();
+    let mut restore_values = Vec::new();
+    for column in restored_columns {
+        let column_name = column.column_name();
+        let member = column.member();
+        let ty = column.ty();
+        restore_types.push(quote!(#diesel::dsl::Eq<#table_name::#column_name, &'query #ty>));
+        restore_values
+            .push(quote!(#diesel::ExpressionMethods::eq(#table_name::#column_name, &self.#member)));
+    }
+    let version_member = model.version_field().member();
+    let bind_type = version_bind_type(model, table_name);
+    restore_types.push(quote!(#diesel::dsl::Eq<#table_name::#version_column, #bind_type>));
+    let column = version_column_expression(model, table_name);
+    let restored_version = version_bind(model, table_name, quote!(self.#version_member));
+    restore_values.push(quote!(#diesel::ExpressionMethods::eq(#column, #restored_version)));
+    let guard = soft_delete_filter(model, false);
+    let restores: Vec<_> = filters
+        .iter()
+        .map(|(filter, _)| {
+            let query = quote! {
+                #diesel::dsl::Filter<
+                    #diesel::dsl::Update<&'query #struct_name #ty_generics, (#(#restore_types,)*)>,
+                    #filter,
+                >
+            };
+            match &guard {
+                Some((guard_type, _)) => quote!(#diesel::dsl::Filter<#query, #guard_type>),
+                None => query,
+            }
+        })
+        .collect();
+    let restore_statement = quote!(#diesel::update(self).set((#(#restore_values,)*)));
+
+    let copy = |execute: TokenStream| {
+        let arms: Vec<_> = copies
+            .iter()
+            .map(|(_, copy)| quote!(#execute(#copy, conn)))
+            .collect();
+        let copy = match_expected_version(&arms);
+        quote! {
+            const COLUMNS: &[&str] = #column_names;
+            #copy
+        }
+    };
+
+    let entity = quote!(#struct_name #ty_generics);
+    let where_clause = generics.make_where_clause();
+    if !impl_async {
+        let copy = copy(quote!(#diesel::RunQueryDsl::execute));
+        let restore = filtered_execute(
+            model,
+            restore_statement,
+            &guard,
+            &quote!(#diesel::RunQueryDsl::execute),
+        );
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel::Connection));
+        for restore_type in &restores {
+            where_clause.predicates.push(parse_quote!(
+                #restore_type: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+            ));
+        }
+        for (copy_type, _) in &copies {
+            where_clause.predicates.push(parse_quote!(
+                #copy_type: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+            ));
+        }
+        for query_type in [&history_type, &current_type, &all_type] {
+            where_clause.predicates.push(parse_quote!(
+                #query_type: #diesel::query_dsl::LoadQuery<'query, CONN, #entity>
+            ));
+        }
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::HistoryStatements<CONN, #version_type, #entity>
+                for &'query #entity
+            #where_clause
+            {
+                fn copy_with_version(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #copy
+                }
+
+                fn load_version(
+                    self,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<#entity> {
+                    match #diesel::RunQueryDsl::get_result(#history_query, conn) {
+                        ::std::result::Result::Err(#diesel::result::Error::NotFound) => {
+                            #diesel::RunQueryDsl::get_result(#current_query, conn)
+                        }
+                        result => result,
+                    }
+                }
+
+                fn load_history(self, conn: &mut CONN) -> #diesel::QueryResult<::std::vec::Vec<#entity>> {
+                    #diesel::RunQueryDsl::load(#all_query, conn)
+                }
+
+                fn restore_with_version(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #restore
+                }
+            }
+        }
+    } else {
+        let copy = copy(quote!(#diesel_async::RunQueryDsl::execute));
+        let restore = filtered_execute(
+            model,
+            restore_statement,
+            &guard,
+            &quote!(#diesel_async::RunQueryDsl::execute),
+        );
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel_async::AsyncConnection + Send));
+        for restore_type in &restores {
+            where_clause.predicates.push(parse_quote!(
+                #restore_type: #diesel_async::methods::ExecuteDsl<CONN>
+            ));
+        }
+        // see derive_versioned_async of an integer version
+        for param in item.generics.type_params() {
+            let ident = &param.ident;
+            where_clause.predicates.push(parse_quote!(#ident: Send));
+        }
+        for (copy_type, _) in &copies {
+            where_clause.predicates.push(parse_quote!(
+                #copy_type: #diesel_async::methods::ExecuteDsl<CONN>
+            ));
+        }
+        for query_type in [&history_type, &current_type, &all_type] {
+            where_clause.predicates.push(parse_quote!(
+                #query_type: #diesel_async::methods::LoadQuery<'query, CONN, #entity>
+            ));
+        }
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::HistoryStatementsAsync<'query, CONN, #version_type, #entity>
+                for &'query #entity
+            #where_clause
+            {
+                fn copy_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #copy
+                }
+
+                fn load_version(
+                    self,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<#entity>> + Send {
+                    // the queries are built before, so the future doesn't hold the entity
+                    let history_query = #history_query;
+                    let current_query = #current_query;
+                    async move {
+                        match #diesel_async::RunQueryDsl::get_result(history_query, conn).await {
+                            ::std::result::Result::Err(#diesel::result::Error::NotFound) => {
+                                #diesel_async::RunQueryDsl::get_result(current_query, conn).await
+                            }
+                            result => result,
+                        }
+                    }
+                }
+
+                fn load_history(
+                    self,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<::std::vec::Vec<#entity>>> + Send {
+                    #diesel_async::RunQueryDsl::load(#all_query, conn)
+                }
+
+                fn restore_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #restore
+                }
+            }
+        }
+    }
+}
+
+///
+/// Implements `VersionHistory` by the history statements.
+///
+pub(crate) fn derive_history(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel::Connection<Backend = #backend>
+    ));
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::HistoryStatements<CONN, #version_type, Self>
+    ));
+    if model.version_field().soft_delete().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::SoftDeleteStatements<CONN, #version_type>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatements<CONN, #version_type>
+        ));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let restore = versioned_write(
+        model,
+        quote!(#krate::__private::HistoryStatements::restore_with_version(&*self, expected_version, conn)),
+        "update",
+        "restored",
+        true,
+        quote!(),
+        false,
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::VersionHistory<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            type Version = #version_type;
+
+            fn load_version(&self, version: #version_type, conn: &mut CONN) -> ::std::result::Result<Self, #diesel::result::Error> {
+                #krate::__private::HistoryStatements::load_version(self, version, conn)
+            }
+
+            fn load_history(&self, conn: &mut CONN) -> ::std::result::Result<::std::vec::Vec<Self>, #diesel::result::Error> {
+                #krate::__private::HistoryStatements::load_history(self, conn)
+            }
+
+            fn restore_to_version(&mut self, version: #version_type, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error> {
+                let mut restored = #krate::__private::HistoryStatements::load_version(&*self, version, conn)?;
+                // written as the successor of the version of self
+                restored.#version_member = self.#version_member;
+                let current = ::std::mem::replace(self, restored);
+                let result = (|| -> ::std::result::Result<(), #diesel::result::Error> { #restore })();
+                if result.is_err() {
+                    *self = current;
+                }
+                result
+            }
+        }
+    }
+}
+
+///
+/// Implements `VersionHistoryAsync` by the history statements.
+///
+pub(crate) fn derive_history_async(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel_async::AsyncConnection<Backend = #backend> + Send
+    ));
+    // see derive_versioned_async of an integer version, the statements within the transaction
+    // share the entity
+    for param in item.generics.type_params() {
+        let ident = &param.ident;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ident: Send + Sync));
+    }
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::HistoryStatementsAsync<'query, CONN, #version_type, Self>
+    ));
+    if model.version_field().soft_delete().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::SoftDeleteStatementsAsync<'query, CONN, #version_type>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatementsAsync<'query, CONN, #version_type>
+        ));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let restore = versioned_write(
+        model,
+        quote!(#krate::__private::HistoryStatementsAsync::restore_with_version(&*self, expected_version, conn)),
+        "update",
+        "restored",
+        true,
+        quote!(),
+        true,
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::VersionHistoryAsync<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            type Version = #version_type;
+
+            fn load_version(
+                &self,
+                version: #version_type,
+                conn: &mut CONN,
+            ) -> impl ::std::future::Future<Output = ::std::result::Result<Self, #diesel::result::Error>> + Send {
+                #krate::__private::HistoryStatementsAsync::load_version(self, version, conn)
+            }
+
+            fn load_history(
+                &self,
+                conn: &mut CONN,
+            ) -> impl ::std::future::Future<Output = ::std::result::Result<::std::vec::Vec<Self>, #diesel::result::Error>> + Send {
+                #krate::__private::HistoryStatementsAsync::load_history(self, conn)
+            }
+
+            async fn restore_to_version(&mut self, version: #version_type, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error> {
+                let mut restored = #krate::__private::HistoryStatementsAsync::load_version(&*self, version, conn).await?;
+                // written as the successor of the version of self
+                restored.#version_member = self.#version_member;
+                let current = ::std::mem::replace(self, restored);
+                let result: ::std::result::Result<(), #diesel::result::Error> = async { #restore }.await;
+                if result.is_err() {
+                    *self = current;
+                }
+                result
+            }
+        }
+    }
+}
@@ -1,29 +1,20 @@
 use proc_macro::TokenStream;
-use proc_macro_error::proc_macro_error;
 use syn::parse_macro_input;
 
+mod history;
 mod model;
+mod outbox;
+mod soft_delete;
 mod versioned;
+mod xmin;
 
 ///
-/// Dervice macro to implement Versioned.
+/// Dervice macro to implement Versioned, and VersionedAsync if the `async` feature of
+/// diesel_versioning is enabled.
 ///
-#[proc_macro_derive(Versioned, attributes(diesel, version))]
-#[proc_macro_error]
+#[proc_macro_derive(Versioned, attributes(diesel, diesel_versioning, version))]
 pub fn derive_versioned(input: TokenStream) -> TokenStream {
-    versioned::derive(parse_macro_input!(input), false)
-        .unwrap_or_else(syn::Error::into_compile_error)
-        .into()
-}
-
-///
-/// Dervice macro to implement VersionedAsync.
-///
-#[cfg(feature = "async")]
-#[proc_macro_derive(VersionedAsync, attributes(diesel, version))]
-#[proc_macro_error]
-pub fn derive_versioned_async(input: TokenStream) -> TokenStream {
-    versioned::derive(parse_macro_input!(input), true)
+    versioned::derive(parse_macro_input!(input))
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
@@ -1,16 +1,20 @@
-use proc_macro_error::abort_call_site;
-use proc_macro2::Ident;
+use proc_macro2::{Ident, TokenTree};
 use syn::{
-    Data, DataStruct, Fields, FieldsNamed, FieldsUnnamed, Path, Result, TypePath,
-    parenthesized,
+    Attribute, Data, DataStruct, Fields, FieldsNamed, FieldsUnnamed, GenericArgument, Index,
+    LitInt, LitStr, Member, Meta, Path, PathArguments, Result, Type, TypePath, parenthesized,
     parse::{Parse, ParseStream, Peek},
+    parse_quote,
     punctuated::Punctuated,
     token::{Comma, Eq},
 };
 
 pub enum StructAttr {
     TableName(Path),
+    PrimaryKey(syn::punctuated::Punctuated<Ident, syn::Token![,]>),
     CheckForBackend(syn::punctuated::Punctuated<TypePath, syn::Token![,]>),
+    /// `treat_none_as_null`, which changes the `AsChangeset` of the struct.
+    TreatNoneAsNull(Ident),
+    Other,
 }
 
 impl Parse for StructAttr {
@@ -19,15 +23,103 @@ impl Parse for StructAttr {
         let name_str = name.to_string();
         match &*name_str {
             "table_name" => Ok(StructAttr::TableName(parse_eq(input)?)),
+            "primary_key" => Ok(StructAttr::PrimaryKey(parse_paren_list(
+                input,
+                syn::Token![,],
+            )?)),
             "check_for_backend" => Ok(StructAttr::CheckForBackend(parse_paren_list(
                 input,
                 syn::Token![,],
             )?)),
-            _ => Err(syn::Error::new(name.span(), "uninteressting attribute")),
+            "treat_none_as_null" => {
+                skip_value(input)?;
+                Ok(StructAttr::TreatNoneAsNull(name))
+            }
+            _ => {
+                skip_value(input)?;
+                Ok(StructAttr::Other)
+            }
+        }
+    }
+}
+
+pub enum VersioningAttr {
+    Crate(Path),
+}
+
+impl Parse for VersioningAttr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(syn::Token![crate]) {
+            input.parse::<syn::Token![crate]>()?;
+            let path: LitStr = parse_eq(input)?;
+            return Ok(VersioningAttr::Crate(path.parse()?));
         }
+        Err(syn::Error::new(
+            input.span(),
+            "unknown diesel_versioning attribute, expected `crate`",
+        ))
     }
 }
 
+pub enum FieldAttr {
+    ColumnName(Ident),
+    /// `skip_update` or `serialize_as`, which change the `AsChangeset` of the struct.
+    Changeset(Ident),
+    Other,
+}
+
+impl Parse for FieldAttr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let name_str = name.to_string();
+        match &*name_str {
+            "column_name" => Ok(FieldAttr::ColumnName(parse_eq(input)?)),
+            "skip_update" | "serialize_as" => {
+                skip_value(input)?;
+                Ok(FieldAttr::Changeset(name))
+            }
+            _ => {
+                skip_value(input)?;
+                Ok(FieldAttr::Other)
+            }
+        }
+    }
+}
+
+pub enum VersionAttr {
+    Initial(LitInt),
+    Xmin(Ident),
+    History(Path),
+    SoftDelete(Ident),
+    Outbox(Path),
+}
+
+impl Parse for VersionAttr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let name_str = name.to_string();
+        match &*name_str {
+            "initial" => Ok(VersionAttr::Initial(parse_eq(input)?)),
+            "xmin" => Ok(VersionAttr::Xmin(name)),
+            "history" => Ok(VersionAttr::History(parse_eq(input)?)),
+            "soft_delete" => Ok(VersionAttr::SoftDelete(parse_eq(input)?)),
+            "outbox" => Ok(VersionAttr::Outbox(parse_eq(input)?)),
+            _ => Err(syn::Error::new(
+                name.span(),
+                "unknown version attribute, expected `initial`, `xmin`, `history`, `soft_delete` or `outbox`",
+            )),
+        }
+    }
+}
+
+/// Skips the value of an attribute we are not interessted in, e.g. `primary_key(a, b)`.
+fn skip_value(input: ParseStream) -> Result<()> {
+    while !input.is_empty() && !input.peek(Comma) {
+        input.parse::<TokenTree>()?;
+    }
+    Ok(())
+}
+
 pub fn parse_eq<T: Parse>(input: ParseStream) -> Result<T> {
     if input.is_empty() {
         return Err(syn::Error::new(
@@ -61,13 +153,271 @@ where
     content.parse_terminated(T::parse, sep)
 }
 
+/// Types supported as version field.
+const VERSION_TYPES: [&str; 3] = ["i16", "i32", "i64"];
+
+pub struct VersionField {
+    member: Member,
+    column_name: Ident,
+    ty: Type,
+    nullable: bool,
+    initial: Option<LitInt>,
+    xmin: Option<Ident>,
+    history: Option<Path>,
+    soft_delete: Option<Ident>,
+    outbox: Option<Path>,
+}
+
+impl VersionField {
+    fn from_field(index: usize, field: &syn::Field, attr: &Attribute) -> Result<Self> {
+        let mut initial = None;
+        let mut xmin = None;
+        let mut history = None;
+        let mut soft_delete = None;
+        let mut outbox = None;
+        if let Meta::List(_) = attr.meta {
+            let map = attr.parse_args_with(Punctuated::<VersionAttr, Comma>::parse_terminated)?;
+            for attr in map.into_iter() {
+                match attr {
+                    VersionAttr::Initial(value) => initial = Some(value),
+                    VersionAttr::Xmin(name) => xmin = Some(name),
+                    VersionAttr::History(path) => history = Some(path),
+                    VersionAttr::SoftDelete(column) => soft_delete = Some(column),
+                    VersionAttr::Outbox(path) => outbox = Some(path),
+                }
+            }
+        }
+        let Column {
+            member,
+            column_name,
+            ..
+        } = Column::from_field(index, field).ok_or_else(|| {
+            syn::Error::new_spanned(
+                field,
+                "tuple struct version field requires #[diesel(column_name = ...)]",
+            )
+        })?;
+        let nullable = if xmin.is_some() {
+            check_xmin_type(&field.ty)?;
+            false
+        } else {
+            check_version_type(&field.ty)?
+        };
+        if let (Some(history), Some(_)) = (&history, &xmin) {
+            // the rows of a history table are identified by the version, which xmin is not
+            return Err(syn::Error::new_spanned(
+                history,
+                "history is not supported for xmin versions",
+            ));
+        }
+        if let (Some(soft_delete), Some(_)) = (&soft_delete, &xmin) {
+            // the tombstone gets a new version, which can't be set for xmin
+            return Err(syn::Error::new_spanned(
+                soft_delete,
+                "soft_delete is not supported for xmin versions",
+            ));
+        }
+        if let (Some(outbox), Some(_)) = (&outbox, &xmin) {
+            // the events are ordered by the version, which xmin is not
+            return Err(syn::Error::new_spanned(
+                outbox,
+                "outbox is not supported for xmin versions",
+            ));
+        }
+        if let (Some(initial), false) = (&initial, nullable) {
+            return Err(syn::Error::new_spanned(
+                initial,
+                "initial is only supported for nullable version fields",
+            ));
+        }
+        Ok(Self {
+            member,
+            column_name,
+            ty: field.ty.clone(),
+            nullable,
+            initial,
+            xmin,
+            history,
+            soft_delete,
+            outbox,
+        })
+    }
+
+    /// Member to access the version field, e.g. `version` or `0`.
+    pub(crate) fn member(&self) -> &Member {
+        &self.member
+    }
+
+    /// Name of the version column in the table.
+    pub(crate) fn column_name(&self) -> &Ident {
+        &self.column_name
+    }
+
+    /// Rust type of the version field, one of [`VERSION_TYPES`] or an `Option` of it.
+    pub(crate) fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    /// Whether the version column is nullable. `NULL` means the row was never versioned.
+    pub(crate) fn nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Version set by the first update of a nullable version, `#[version(initial = ...)]`.
+    pub(crate) fn initial(&self) -> Option<&LitInt> {
+        self.initial.as_ref()
+    }
+
+    /// Whether the version is the Postgres system column `xmin`, `#[version(xmin)]`.
+    pub(crate) fn xmin(&self) -> bool {
+        self.xmin.is_some()
+    }
+
+    /// Table keeping the replaced versions, `#[version(history = ...)]`.
+    pub(crate) fn history(&self) -> Option<&Path> {
+        self.history.as_ref()
+    }
+
+    /// Nullable column marking a deleted row, `#[version(soft_delete = ...)]`.
+    pub(crate) fn soft_delete(&self) -> Option<&Ident> {
+        self.soft_delete.as_ref()
+    }
+
+    /// Table receiving an event of every write, `#[version(outbox = ...)]`.
+    pub(crate) fn outbox(&self) -> Option<&Path> {
+        self.outbox.as_ref()
+    }
+}
+
+/// Field of the struct mapped to a column.
+#[derive(Clone)]
+pub struct Column {
+    member: Member,
+    column_name: Ident,
+    ty: Type,
+    changeset_attrs: Vec<Ident>,
+}
+
+impl Column {
+    /// Maps the field to the column of `#[diesel(column_name = ...)]` or of the same name. `None`
+    /// for a field of a tuple struct without a column name.
+    fn from_field(index: usize, field: &syn::Field) -> Option<Self> {
+        let mut column_name = None;
+        let mut changeset_attrs = Vec::new();
+        for attr in &field.attrs {
+            if attr.meta.path().is_ident("diesel") {
+                let map = attr.parse_args_with(Punctuated::<FieldAttr, Comma>::parse_terminated);
+                if let Ok(map) = map {
+                    for attr in map.into_iter() {
+                        match attr {
+                            FieldAttr::ColumnName(name) => column_name = Some(name),
+                            FieldAttr::Changeset(name) => changeset_attrs.push(name),
+                            FieldAttr::Other => {}
+                        }
+                    }
+                }
+            }
+        }
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        let column_name = column_name.or_else(|| field.ident.clone())?;
+        Some(Self {
+            member,
+            column_name,
+            ty: field.ty.clone(),
+            changeset_attrs,
+        })
+    }
+
+    /// Member to access the field, e.g. `body` or `2`.
+    pub(crate) fn member(&self) -> &Member {
+        &self.member
+    }
+
+    /// Name of the column in the table.
+    pub(crate) fn column_name(&self) -> &Ident {
+        &self.column_name
+    }
+
+    /// Rust type of the field.
+    pub(crate) fn ty(&self) -> &Type {
+        &self.ty
+    }
+}
+
+/// Checks the type of a `xmin` version field syntactically, which has to be written as
+/// `TransactionId`, optionally with its path.
+fn check_xmin_type(ty: &Type) -> Result<()> {
+    match ty {
+        Type::Path(TypePath { qself: None, path })
+            if path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "TransactionId") =>
+        {
+            Ok(())
+        }
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "unsupported xmin version type, expected diesel_versioning::pg::TransactionId",
+        )),
+    }
+}
+
+/// Checks the type of the version field syntactically, as the derive can't resolve types. So a
+/// version must be written as `i16`, `i32` or `i64` (optionally wrapped by `Option`) and not e.g. by
+/// an alias. Returns whether the version is nullable.
+fn check_version_type(ty: &Type) -> Result<bool> {
+    let segment = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last(),
+        _ => None,
+    };
+    match segment {
+        Some(segment)
+            if segment.arguments.is_none()
+                && VERSION_TYPES.iter().any(|name| segment.ident == name) =>
+        {
+            Ok(false)
+        }
+        Some(segment) if segment.ident == "Option" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+                GenericArgument::Type(inner) => match check_version_type(inner)? {
+                    false => Ok(true),
+                    true => Err(syn::Error::new_spanned(
+                        inner,
+                        "nested Option is not supported as version",
+                    )),
+                },
+                arg => Err(syn::Error::new_spanned(arg, "expected a version type")),
+            },
+            _ => Err(syn::Error::new_spanned(ty, "expected a version type")),
+        },
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            format!(
+                "unsupported version type, expected one of {}",
+                VERSION_TYPES.join(", ")
+            ),
+        )),
+    }
+}
+
 pub struct Model {
-    version_fieldname: Option<Ident>,
-    table_names: Vec<Path>,
+    krate: Path,
+    version_field: VersionField,
+    table_name: Path,
     backends: Vec<Path>,
+    primary_key: Vec<Ident>,
+    columns: Vec<Column>,
+    changeset: Vec<Column>,
 }
 impl Model {
+    /// Parses the struct. All problems found are reported at once, each spanned on the offending
+    /// field or attribute.
     pub(crate) fn from_item(item: &syn::DeriveInput) -> Result<Self> {
+        let mut errors = Errors::default();
         // extract fields of struct
         let fields = match &item.data {
             Data::Struct(DataStruct {
@@ -78,77 +428,275 @@ impl Model {
                 fields: Fields::Unnamed(FieldsUnnamed { unnamed, .. }),
                 ..
             }) => Some(unnamed),
-            _ => None,
-        };
-        let version_fieldname = match fields {
-            Some(fields) => {
-                let version_fields: Vec<_> = fields
-                    .iter()
-                    .filter(|f| {
-                        if f.attrs
-                            .iter()
-                            .filter(|attr| attr.path().is_ident("version"))
-                            .count()
-                            == 1
-                        {
-                            return true;
-                        }
-                        false
-                    })
-                    .collect();
-                match version_fields.len() {
-                    0 => None,
-                    1 => version_fields
-                        .first()
-                        .expect("first element on non empty list")
-                        .ident
-                        .to_owned(),
-                    2.. => abort_call_site!("only one version field is supported"),
-                }
+            _ => {
+                errors.push(syn::Error::new_spanned(
+                    &item.ident,
+                    "Versioned can only be derived for structs with fields",
+                ));
+                None
             }
-            None => None,
         };
+        let mut version_field = None;
+        let mut version_attrs = 0;
+        let mut columns = Vec::new();
+        for (index, field) in fields.into_iter().flatten().enumerate() {
+            let attrs: Vec<_> = field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("version"))
+                .collect();
+            let Some(attr) = attrs.first() else {
+                columns.push((index, field, false));
+                continue;
+            };
+            columns.push((index, field, true));
+            for duplicate in &attrs[1..] {
+                errors.push(syn::Error::new_spanned(
+                    duplicate,
+                    "duplicate #[version] attribute",
+                ));
+            }
+            version_attrs += 1;
+            if version_attrs > 1 {
+                errors.push(syn::Error::new_spanned(
+                    attr,
+                    "only one version field is supported",
+                ));
+                continue;
+            }
+            match VersionField::from_field(index, field, attr) {
+                Ok(field) => version_field = Some(field),
+                Err(err) => errors.push(err),
+            }
+        }
+        if fields.is_some() && version_attrs == 0 {
+            errors.push(syn::Error::new_spanned(
+                &item.ident,
+                "no field is attributed with #[version]",
+            ));
+        }
         //parse attributes
-        let mut table_names = Vec::new();
+        let mut table_name = None;
+        let mut primary_key = None;
         let mut backends = Vec::new();
+        let mut treat_none_as_null = None;
+        let mut krate = None;
         let attrs = &item.attrs;
         for attr in attrs {
+            if attr.meta.path().is_ident("diesel_versioning") {
+                match attr.parse_args_with(Punctuated::<VersioningAttr, Comma>::parse_terminated) {
+                    Ok(map) => {
+                        for attr in map.into_iter() {
+                            match attr {
+                                VersioningAttr::Crate(path) => krate = Some(path),
+                            }
+                        }
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
             if attr.meta.path().is_ident("diesel") {
                 let map = attr.parse_args_with(Punctuated::<StructAttr, Comma>::parse_terminated);
                 if let Ok(map) = map {
                     for attr in map.into_iter() {
                         match attr {
-                            StructAttr::TableName(path) => {
-                                table_names.push(path);
+                            StructAttr::TableName(path) if table_name.is_some() => {
+                                // diesel's AsChangeset and Identifiable only support a single
+                                // table, so there is no other table to version
+                                errors.push(syn::Error::new_spanned(
+                                    path,
+                                    "expected a single table_name, as AsChangeset and Identifiable only support one table",
+                                ));
                             }
+                            StructAttr::TableName(path) => table_name = Some(path),
+                            StructAttr::PrimaryKey(keys) => primary_key = Some(keys),
                             StructAttr::CheckForBackend(path) => {
                                 backends.extend(path.into_iter().map(|backend| backend.path));
                             }
+                            StructAttr::TreatNoneAsNull(name) => treat_none_as_null = Some(name),
+                            StructAttr::Other => {}
                         }
                     }
                 }
             }
         }
+        if table_name.is_none() {
+            errors.push(syn::Error::new_spanned(
+                &item.ident,
+                "expected #[diesel(table_name = ...)]",
+            ));
+        }
+        let krate = krate.unwrap_or_else(|| parse_quote!(::diesel_versioning));
+        let primary_key: Vec<Ident> = match primary_key {
+            Some(keys) => keys.into_iter().collect(),
+            None => vec![parse_quote!(id)],
+        };
+        // of the #[version(...)] keys, the columns are only named by xmin, history and soft_delete,
+        // so only they require column names for the fields of a tuple struct. initial names none
+        // and outbox serializes the entity by serde
+        let mut all_columns = Vec::new();
+        let feature = version_field.as_ref().and_then(|field| {
+            match (field.xmin(), field.history(), field.soft_delete()) {
+                (true, _, _) => Some("xmin"),
+                (false, Some(_), _) => Some("history"),
+                (false, None, Some(_)) => Some("soft_delete"),
+                (false, None, None) => None,
+            }
+        });
+        if let Some(feature) = feature {
+            for (index, field, version) in columns {
+                match Column::from_field(index, field) {
+                    Some(column) => all_columns.push((column, version)),
+                    // the version field itself is already reported
+                    None if version => {}
+                    None => errors.push(syn::Error::new_spanned(
+                        field,
+                        format!(
+                            "tuple struct field requires #[diesel(column_name = ...)] for {feature}"
+                        ),
+                    )),
+                }
+            }
+        }
+        if let Some(history) = version_field.as_ref().and_then(VersionField::history) {
+            // the history is filtered by the primary key, so it has to be known
+            for key in &primary_key {
+                if !all_columns
+                    .iter()
+                    .any(|(column, _)| column.column_name() == key)
+                {
+                    errors.push(syn::Error::new_spanned(
+                        history,
+                        format!("history requires a field for the primary key column `{key}`"),
+                    ));
+                }
+            }
+        }
+        // xmin is maintained by Postgres and can't be set, so the other columns are written by an
+        // explicit changeset instead of the AsChangeset of the struct
+        let mut changeset = Vec::new();
+        if let Some(version_field) = version_field.as_ref().filter(|field| field.xmin()) {
+            for backend in &backends {
+                if backend
+                    .segments
+                    .last()
+                    .is_none_or(|segment| segment.ident != "Pg")
+                {
+                    errors.push(syn::Error::new_spanned(
+                        backend,
+                        "xmin is only supported by Postgres",
+                    ));
+                }
+            }
+            if backends.is_empty() {
+                backends.push(parse_quote!(#krate::__private::diesel::pg::Pg));
+            }
+            changeset.extend(
+                all_columns
+                    .iter()
+                    .filter(|(column, version)| {
+                        !version && !primary_key.contains(column.column_name())
+                    })
+                    .map(|(column, _)| column.clone()),
+            );
+            if changeset.is_empty() {
+                errors.push(syn::Error::new_spanned(
+                    &version_field.member,
+                    "xmin requires a column to update besides the primary key",
+                ));
+            }
+            // the explicit changeset writes every field as it is, so the attributes changing the
+            // AsChangeset of the struct would be ignored silently
+            let changeset_attrs = all_columns
+                .iter()
+                .flat_map(|(column, _)| &column.changeset_attrs)
+                .chain(&treat_none_as_null);
+            for name in changeset_attrs {
+                errors.push(syn::Error::new_spanned(
+                    name,
+                    format!(
+                        "#[diesel({name})] is not supported by xmin, which writes every column but the primary key"
+                    ),
+                ));
+            }
+        }
+        errors.finish()?;
         Ok(Self {
-            version_fieldname,
-            table_names,
+            krate,
+            version_field: version_field.expect("version field is checked above"),
+            table_name: table_name.expect("table name is checked above"),
             backends,
+            primary_key,
+            columns: all_columns.into_iter().map(|(column, _)| column).collect(),
+            changeset,
+        })
+    }
+
+    /// Path of the diesel_versioning crate, `#[diesel_versioning(crate = "...")]`.
+    pub(crate) fn krate(&self) -> &Path {
+        &self.krate
+    }
+
+    pub(crate) fn version_field(&self) -> &VersionField {
+        &self.version_field
+    }
+
+    /// Table of `table_name`, holding the version column.
+    pub(crate) fn table_name(&self) -> &Path {
+        &self.table_name
+    }
+    /// Backends of `check_for_backend`, may be empty.
+    pub(crate) fn backends(&self) -> &[Path] {
+        &self.backends
+    }
+
+    /// Columns of all fields in the order of the struct, including the version. Only collected for
+    /// entities with `xmin`, `history` or `soft_delete`, empty for other entities, as `initial`
+    /// names no column and `outbox` serializes the entity.
+    pub(crate) fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Columns of the primary key, which are mapped to a field of the struct.
+    pub(crate) fn primary_key(&self) -> impl Iterator<Item = &Column> {
+        self.primary_key.iter().filter_map(|key| {
+            self.columns
+                .iter()
+                .find(|column| column.column_name() == key)
         })
     }
-    pub(crate) fn version_fieldname(&self) -> &Option<Ident> {
-        &self.version_fieldname
+
+    /// Field of the soft delete column, if the entity maps it.
+    pub(crate) fn soft_delete_field(&self) -> Option<&Column> {
+        let soft_delete = self.version_field.soft_delete()?;
+        self.columns
+            .iter()
+            .find(|column| column.column_name() == soft_delete)
     }
 
-    pub(crate) fn table_names(&self) -> &[Path] {
-        match self.table_names.len() {
-            0 => abort_call_site!("exptected table_name, but didn't found one"),
-            _ => &self.table_names,
+    /// Columns written by an update of a `xmin` versioned entity, all but the primary key and
+    /// version. Empty for other entities.
+    pub(crate) fn changeset(&self) -> &[Column] {
+        &self.changeset
+    }
+}
+
+/// Collects errors, so all of them are reported at once.
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+
+impl Errors {
+    fn push(&mut self, error: syn::Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
         }
     }
-    pub(crate) fn backends(&self) -> &[Path] {
-        match self.backends.len() {
-            0 => abort_call_site!("exptected backend, but didn't found one"),
-            _ => &self.backends,
+
+    fn finish(self) -> Result<()> {
+        match self.0 {
+            Some(errors) => Err(errors),
+            None => Ok(()),
         }
     }
 }
@@ -0,0 +1,146 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{DeriveInput, Path, parse_quote};
+
+use crate::model::Model;
+use crate::versioned::{diesel_async_path, diesel_path};
+
+///
+/// Static assertion, that the outbox table has the columns of an event. Spanned on the path of the
+/// attribute, like the check of the version column.
+///
+pub(crate) fn check_outbox_table(model: &Model, outbox: &Path) -> TokenStream {
+    let krate = model.krate();
+    quote_spanned! {outbox.span()=>
+        const _: () = #krate::__private::check_outbox_table::<
+            #outbox::entity_type,
+            #outbox::entity_id,
+            #outbox::version,
+            #outbox::operation,
+            #outbox::payload,
+        >();
+    }
+}
+
+///
+/// Implements the insert of an event into the outbox table for a reference to the entity, see
+/// `derive_statements` of an integer version. The id and the entity are serialized as JSON.
+///
+pub(crate) fn derive_statements(
+    item: &DeriveInput,
+    model: &Model,
+    impl_async: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_type = model.version_field().ty();
+    let outbox = model
+        .version_field()
+        .outbox()
+        .expect("statements are only derived with an outbox table");
+    let entity_type = struct_name.to_string();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = item.generics.clone();
+    generics.params.insert(0, parse_quote!('query));
+    generics.params.push(parse_quote!(CONN));
+
+    let entity = quote!(#struct_name #ty_generics);
+    let query_type = quote! {
+        #diesel::dsl::Values<
+            #diesel::dsl::insert_into<#outbox::table>,
+            (
+                #diesel::dsl::Eq<#outbox::entity_type, &'static str>,
+                #diesel::dsl::Eq<#outbox::entity_id, ::std::string::String>,
+                #diesel::dsl::Eq<#outbox::version, i64>,
+                #diesel::dsl::Eq<#outbox::operation, &'static str>,
+                #diesel::dsl::Eq<#outbox::payload, ::std::string::String>,
+            ),
+        >
+    };
+    let event = quote! {
+        let entity_id = #krate::__private::to_json(&#diesel::Identifiable::id(self))?;
+        let payload = #krate::__private::to_json(self)?;
+        let query = #diesel::insert_into(#outbox::table).values((
+            #diesel::ExpressionMethods::eq(#outbox::entity_type, #entity_type),
+            #diesel::ExpressionMethods::eq(#outbox::entity_id, entity_id),
+            #diesel::ExpressionMethods::eq(
+                #outbox::version,
+                #krate::__private::EventVersion::event_version(version),
+            ),
+            #diesel::ExpressionMethods::eq(#outbox::operation, operation),
+            #diesel::ExpressionMethods::eq(#outbox::payload, payload),
+        ));
+    };
+
+    let where_clause = generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(#entity: #krate::__private::serde::Serialize));
+    where_clause.predicates.push(parse_quote!(
+        <&'query #entity as #diesel::Identifiable>::Id: #krate::__private::serde::Serialize
+    ));
+    if !impl_async {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel::Connection));
+        where_clause.predicates.push(parse_quote!(
+            #query_type: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+        ));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::OutboxStatements<CONN, #version_type>
+                for &'query #entity
+            #where_clause
+            {
+                fn publish(
+                    self,
+                    operation: &'static str,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #event
+                    #diesel::RunQueryDsl::execute(query, conn)
+                }
+            }
+        }
+    } else {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel_async::AsyncConnection + Send));
+        // see derive_versioned_async of an integer version
+        for param in item.generics.type_params() {
+            let ident = &param.ident;
+            where_clause.predicates.push(parse_quote!(#ident: Send));
+        }
+        where_clause.predicates.push(parse_quote!(
+            #query_type: #diesel_async::methods::ExecuteDsl<CONN>
+        ));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::OutboxStatementsAsync<'query, CONN, #version_type>
+                for &'query #entity
+            #where_clause
+            {
+                fn publish(
+                    self,
+                    operation: &'static str,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<usize>> + Send {
+                    // the event is serialized before, so the future doesn't hold the entity
+                    let event = (|| -> #diesel::QueryResult<_> {
+                        #event
+                        ::std::result::Result::Ok(query)
+                    })();
+                    async move { #diesel_async::RunQueryDsl::execute(event?, conn).await }
+                }
+            }
+        }
+    }
+}
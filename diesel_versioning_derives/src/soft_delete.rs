@@ -0,0 +1,376 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{DeriveInput, parse_quote};
+
+use crate::model::Model;
+use crate::versioned::{
+    diesel_async_path, diesel_path, filtered_execute, impl_generics, soft_delete_column,
+    soft_delete_filter, version_bind, version_bind_type, version_column_expression,
+    version_filters, versioned_write,
+};
+
+///
+/// Static assertion, that the soft delete column exists and is nullable. Spanned on the column of
+/// the attribute, like the check of the version column.
+///
+pub(crate) fn check_soft_delete_column(model: &Model, column: &Ident) -> TokenStream {
+    let krate = model.krate();
+    let table_name = model.table_name();
+    quote_spanned! {column.span()=>
+        const _: () = #krate::__private::check_soft_delete_column::<#table_name::#column>();
+    }
+}
+
+///
+/// Implements the statements of the soft delete for a reference to the entity, see
+/// `derive_statements` of an integer version.
+///
+pub(crate) fn derive_statements(
+    item: &DeriveInput,
+    model: &Model,
+    impl_async: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    let table_name = model.table_name();
+    let (column_type, column) =
+        soft_delete_column(model).expect("statements are only derived with a soft delete column");
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = item.generics.clone();
+    generics.params.insert(0, parse_quote!('query));
+    generics.params.push(parse_quote!(CONN));
+
+    let entity = quote!(#struct_name #ty_generics);
+    let version = version_bind_type(model, table_name);
+    let set_version = {
+        let column = version_column_expression(model, table_name);
+        let version = version_bind(model, table_name, quote!(version));
+        quote!(#diesel::ExpressionMethods::eq(#column, #version))
+    };
+    // the Rust type of the column is unknown, so the mark is cleared by a NULL of its SQL type
+    let null = quote!(#diesel::dsl::sql::<#diesel::dsl::SqlTypeOf<#column_type>>("NULL"));
+    let delete_statement = quote! {
+        #diesel::update(self).set((
+            #diesel::ExpressionMethods::eq(#column, #diesel::dsl::now),
+            #set_version,
+        ))
+    };
+    let undelete_statement = quote! {
+        #diesel::update(self).set((
+            #diesel::ExpressionMethods::eq(#column, #null),
+            #set_version,
+        ))
+    };
+    let not_deleted = soft_delete_filter(model, false);
+    let deleted = soft_delete_filter(model, true);
+
+    let filters: Vec<_> = version_filters(model)
+        .into_iter()
+        .map(|(filter, _)| filter)
+        .collect();
+    let query = |value: TokenStream, guard: &Option<(TokenStream, TokenStream)>| {
+        let (guard_type, _) = guard.as_ref().expect("soft delete column is checked above");
+        filters
+            .iter()
+            .map(|filter| {
+                quote! {
+                    #diesel::dsl::Filter<
+                        #diesel::dsl::Filter<
+                            #diesel::dsl::Update<
+                                &'query #entity,
+                                (#diesel::dsl::Eq<#column_type, #value>, #diesel::dsl::Eq<#table_name::#version_column, #version>),
+                            >,
+                            #filter,
+                        >,
+                        #guard_type,
+                    >
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let queries: Vec<_> = query(quote!(#diesel::dsl::now), &not_deleted)
+        .into_iter()
+        .chain(query(
+            quote!(#diesel::expression::SqlLiteral<#diesel::dsl::SqlTypeOf<#column_type>>),
+            &deleted,
+        ))
+        .collect();
+    let deleted_type = quote! {
+        #diesel::dsl::Count<
+            #diesel::dsl::Filter<
+                #diesel::dsl::Find<
+                    #table_name::table,
+                    <&'query #entity as #diesel::Identifiable>::Id,
+                >,
+                #diesel::dsl::IsNotNull<#column_type>,
+            >,
+        >
+    };
+    let deleted_query = quote! {
+        #diesel::QueryDsl::count(#diesel::QueryDsl::filter(
+            #diesel::QueryDsl::find(#table_name::table, #diesel::Identifiable::id(self)),
+            #diesel::ExpressionMethods::is_not_null(#column),
+        ))
+    };
+
+    let where_clause = generics.make_where_clause();
+    if !impl_async {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel::Connection));
+        for query in &queries {
+            where_clause.predicates.push(parse_quote!(
+                #query: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+            ));
+        }
+        where_clause.predicates.push(parse_quote!(
+            #deleted_type: #diesel::query_dsl::LoadQuery<'query, CONN, i64>
+        ));
+        let execute = quote!(#diesel::RunQueryDsl::execute);
+        let delete = filtered_execute(model, delete_statement, &not_deleted, &execute);
+        let undelete = filtered_execute(model, undelete_statement, &deleted, &execute);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::SoftDeleteStatements<CONN, #version_type>
+                for &'query #entity
+            #where_clause
+            {
+                fn delete_with_version(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #delete
+                }
+
+                fn undelete_with_version(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #undelete
+                }
+
+                fn load_deleted(self, conn: &mut CONN) -> #diesel::QueryResult<bool> {
+                    let rows: i64 = #diesel::RunQueryDsl::get_result(#deleted_query, conn)?;
+                    ::std::result::Result::Ok(rows > 0)
+                }
+            }
+        }
+    } else {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel_async::AsyncConnection + Send));
+        // see derive_versioned_async of an integer version
+        for param in item.generics.type_params() {
+            let ident = &param.ident;
+            where_clause.predicates.push(parse_quote!(#ident: Send));
+        }
+        for query in &queries {
+            where_clause.predicates.push(parse_quote!(
+                #query: #diesel_async::methods::ExecuteDsl<CONN>
+            ));
+        }
+        where_clause.predicates.push(parse_quote!(
+            #deleted_type: #diesel_async::methods::LoadQuery<'query, CONN, i64>
+        ));
+        let execute = quote!(#diesel_async::RunQueryDsl::execute);
+        let delete = filtered_execute(model, delete_statement, &not_deleted, &execute);
+        let undelete = filtered_execute(model, undelete_statement, &deleted, &execute);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::SoftDeleteStatementsAsync<'query, CONN, #version_type>
+                for &'query #entity
+            #where_clause
+            {
+                fn delete_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #delete
+                }
+
+                fn undelete_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #undelete
+                }
+
+                fn load_deleted(
+                    self,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<bool>> + Send {
+                    // the query is built before, so the future doesn't hold the entity
+                    let query = #deleted_query;
+                    async move {
+                        let rows: i64 = #diesel_async::RunQueryDsl::get_result(query, conn).await?;
+                        ::std::result::Result::Ok(rows > 0)
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// Clears the field of the soft delete column, if the entity maps it.
+///
+fn clear_field(model: &Model) -> TokenStream {
+    match model.soft_delete_field() {
+        Some(field) => {
+            let member = field.member();
+            quote!(self.#member = ::std::option::Option::None;)
+        }
+        None => quote!(),
+    }
+}
+
+///
+/// Implements `SoftDelete` by the soft delete statements.
+///
+pub(crate) fn derive_soft_delete(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+    let clear_field = clear_field(model);
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel::Connection<Backend = #backend>
+    ));
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::SoftDeleteStatements<CONN, #version_type>
+    ));
+    if model.version_field().history().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::HistoryStatements<CONN, #version_type, Self>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatements<CONN, #version_type>
+        ));
+    }
+    where_clause
+        .predicates
+        .push(parse_quote!(Self: #krate::Versioned<CONN, #backend>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    // a conflict of undelete is the row not being deleted, so it isn't checked for a deleted row
+    let undelete = versioned_write(
+        model,
+        quote!(#krate::__private::SoftDeleteStatements::undelete_with_version(&*self, expected_version, self.#version_member, conn)),
+        "undelete",
+        "undeleted",
+        false,
+        clear_field,
+        false,
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::SoftDelete<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            fn undelete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #undelete
+            }
+        }
+    }
+}
+
+///
+/// Implements `SoftDeleteAsync` by the soft delete statements.
+///
+pub(crate) fn derive_soft_delete_async(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+    let clear_field = clear_field(model);
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel_async::AsyncConnection<Backend = #backend> + Send
+    ));
+    // see derive_versioned_async of an integer version
+    for param in item.generics.type_params() {
+        let ident = &param.ident;
+        where_clause.predicates.push(parse_quote!(#ident: Send));
+    }
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::SoftDeleteStatementsAsync<'query, CONN, #version_type>
+    ));
+    if model.version_field().history().is_some() || model.version_field().outbox().is_some() {
+        for param in item.generics.type_params() {
+            let ident = &param.ident;
+            where_clause.predicates.push(parse_quote!(#ident: Sync));
+        }
+    }
+    if model.version_field().history().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::HistoryStatementsAsync<'query, CONN, #version_type, Self>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatementsAsync<'query, CONN, #version_type>
+        ));
+    }
+    where_clause
+        .predicates
+        .push(parse_quote!(Self: #krate::VersionedAsync<CONN, #backend>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    // a conflict of undelete is the row not being deleted, so it isn't checked for a deleted row
+    let undelete = versioned_write(
+        model,
+        quote!(#krate::__private::SoftDeleteStatementsAsync::undelete_with_version(&*self, expected_version, self.#version_member, conn)),
+        "undelete",
+        "undeleted",
+        false,
+        clear_field,
+        true,
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::SoftDeleteAsync<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            async fn undelete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #undelete
+            }
+        }
+    }
+}
@@ -1,132 +1,883 @@
-use proc_macro_error::abort_call_site;
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{DeriveInput, Result};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{DeriveInput, Generics, Path, Result, parse_quote};
 
 use crate::model::Model;
+use crate::{history, outbox, soft_delete, xmin};
 
-pub fn derive(item: DeriveInput, impl_async: bool) -> Result<TokenStream> {
+pub fn derive(item: DeriveInput) -> Result<TokenStream> {
     let model = Model::from_item(&item)?;
+    if model.version_field().xmin() {
+        return Ok(xmin::derive(&item, &model));
+    }
+    let krate = model.krate();
 
-    let struct_name = &item.ident;
-    let version_fieldname = match model.version_fieldname() {
-        Some(fieldname) => fieldname,
-        None => {
-            abort_call_site!("no field is attributed with #[version]");
-        }
+    // without check_for_backend a single impl generic over the backend is derived
+    let generic_backend = model.backends().is_empty();
+    let backends: Vec<TokenStream> = if generic_backend {
+        vec![quote!(DB)]
+    } else {
+        model
+            .backends()
+            .iter()
+            .map(|backend| quote!(#backend))
+            .collect()
     };
-    let table_name = &model.table_names()[0];
-    let backends = model.backends();
 
-    let code: Vec<TokenStream> = backends
+    let statements = derive_statements(&item, &model, false);
+    let code = backends
         .iter()
-        .map(|backend| {
-            if !impl_async {
-                quote! {
-                    #[automatically_derived]
-                    impl<CONN> Versioned<CONN, #backend> for #struct_name
-                    where
-                        CONN: diesel::Connection<Backend = #backend>,
-                        i32: diesel::serialize::ToSql<diesel::sql_types::Integer, #backend>,
-                    {
-                        fn update_versioned(&mut self, conn: &mut CONN) -> std::result::Result<(), diesel::result::Error>
-                        {
-                            let expected_version = self.#version_fieldname;
-                            //FIXME: Frank: increment anpassen
-                            self.#version_fieldname += 1;
-                            let q = diesel::update(&*self)
-                                .set(&*self)
-                                .filter(#table_name::#version_fieldname.eq(expected_version));
-                            let updated_rows = q.execute(conn)?;
-                            if updated_rows != 1 {
-                                return Err(diesel::result::Error::DatabaseError(
-                                    diesel::result::DatabaseErrorKind::CheckViolation,
-                                    Box::new(format!(
-                                        "optimistic locking: updated {} rows. expected 1",
-                                        updated_rows
-                                    )),
-                                ));
-                            }
-                            Ok(())
-                        }
-
-                        fn delete_versioned(&mut self, conn: &mut CONN) -> std::result::Result<(), diesel::result::Error>
-                        {
-                            let expected_version = self.#version_fieldname;
-                            let q = diesel::delete(&*self).filter(#table_name::#version_fieldname.eq(expected_version));
-                            let updated_rows = q.execute(conn)?;
-                            if updated_rows != 1 {
-                                return Err(diesel::result::Error::DatabaseError(
-                                    diesel::result::DatabaseErrorKind::CheckViolation,
-                                    Box::new(format!(
-                                        "optimistic locking: deleted {} rows. expected 1",
-                                        updated_rows
-                                    )),
-                                ));
-                            }
-                            Ok(())
-                        }
-                    }
+        .map(|backend| derive_versioned(&item, &model, backend, generic_backend));
+    let check = check_version_column(&model);
+    let mut result = quote! {
+        #check
+        #statements
+        #(#code)*
+    };
+    if let Some(history_table) = model.version_field().history() {
+        let check = history::check_history_columns(&model, history_table);
+        let statements = history::derive_statements(&item, &model, false);
+        let code = backends
+            .iter()
+            .map(|backend| history::derive_history(&item, &model, backend, generic_backend));
+        result.extend(quote! {
+            #check
+            #statements
+            #(#code)*
+        });
+    }
+    if let Some(column) = model.version_field().soft_delete() {
+        let check = soft_delete::check_soft_delete_column(&model, column);
+        let statements = soft_delete::derive_statements(&item, &model, false);
+        let code = backends.iter().map(|backend| {
+            soft_delete::derive_soft_delete(&item, &model, backend, generic_backend)
+        });
+        result.extend(quote! {
+            #check
+            #statements
+            #(#code)*
+        });
+    }
+    if let Some(outbox_table) = model.version_field().outbox() {
+        let check = outbox::check_outbox_table(&model, outbox_table);
+        let statements = outbox::derive_statements(&item, &model, false);
+        result.extend(quote! {
+            #check
+            #statements
+        });
+    }
+    // the async impls are expanded depending on the features of diesel_versioning
+    let statements = derive_statements(&item, &model, true);
+    let code = backends
+        .iter()
+        .map(|backend| derive_versioned_async(&item, &model, backend, generic_backend));
+    let history = model.version_field().history().map(|_| {
+        let statements = history::derive_statements(&item, &model, true);
+        let code = backends
+            .iter()
+            .map(|backend| history::derive_history_async(&item, &model, backend, generic_backend));
+        quote! {
+            #statements
+            #(#code)*
+        }
+    });
+    let soft_delete = model.version_field().soft_delete().map(|_| {
+        let statements = soft_delete::derive_statements(&item, &model, true);
+        let code = backends.iter().map(|backend| {
+            soft_delete::derive_soft_delete_async(&item, &model, backend, generic_backend)
+        });
+        quote! {
+            #statements
+            #(#code)*
+        }
+    });
+    let outbox = model
+        .version_field()
+        .outbox()
+        .map(|_| outbox::derive_statements(&item, &model, true));
+    result.extend(quote! {
+        #krate::__private::if_async! {
+            #statements
+            #(#code)*
+            #history
+            #soft_delete
+            #outbox
+        }
+    });
+    Ok(result)
+}
+
+///
+/// Static assertion, that the version column exists and its SQL type matches the version field.
+/// Spanned on the field, so a wrong column is reported there by a single readable error.
+///
+pub(crate) fn check_version_column(model: &Model) -> TokenStream {
+    let krate = model.krate();
+    let table_name = model.table_name();
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    quote_spanned! {version_type.span()=>
+        const _: () = #krate::__private::check_version_column::<#table_name::#version_column, #version_type>();
+    }
+}
+
+///
+/// Type of the bind of a version to a statement on the version column of `table`. Its type isn't
+/// checked against the column, so a wrong column is reported by the check of the column alone and
+/// not again by every statement.
+///
+pub(crate) fn version_bind_type(model: &Model, table: &Path) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    quote!(#krate::__private::VersionBind<#diesel::dsl::SqlTypeOf<#table::#version_column>, #version_type>)
+}
+
+///
+/// Binds `version` to a statement on the version column of `table`, see `version_bind_type`.
+///
+pub(crate) fn version_bind(model: &Model, table: &Path, version: TokenStream) -> TokenStream {
+    let krate = model.krate();
+    let version_column = model.version_field().column_name();
+    quote!(#krate::__private::version_bind::<#table::#version_column, _>(#version))
+}
+
+///
+/// Version column of `table` as expression. The column is only named as type, so a missing one is
+/// reported once by the check of the column.
+///
+pub(crate) fn version_column_expression(model: &Model, table: &Path) -> TokenStream {
+    let version_column = model.version_field().column_name();
+    quote!(<#table::#version_column as ::std::default::Default>::default())
+}
+
+///
+/// Filters restricting a statement to `expected_version` as type and as expression. A nullable
+/// version which was never set is matched by a second filter of `IS NULL`, see
+/// `match_expected_version`.
+///
+pub(crate) fn version_filters(model: &Model) -> Vec<(TokenStream, TokenStream)> {
+    let diesel = diesel_path(model);
+    let table_name = model.table_name();
+    let version_column = model.version_field().column_name();
+    let column = version_column_expression(model, table_name);
+    let bind_type = version_bind_type(model, table_name);
+    let bind = version_bind(model, table_name, quote!(expected_version));
+    let mut filters = vec![(
+        quote!(#diesel::dsl::Eq<#table_name::#version_column, #bind_type>),
+        quote!(#diesel::ExpressionMethods::eq(#column, #bind)),
+    )];
+    if model.version_field().nullable() {
+        filters.push((
+            quote!(#diesel::dsl::IsNull<#table_name::#version_column>),
+            quote!(#diesel::ExpressionMethods::is_null(#column)),
+        ));
+    }
+    filters
+}
+
+///
+/// Matches `expected_version` to the arm of its filter of `version_filters`.
+///
+pub(crate) fn match_expected_version(arms: &[TokenStream]) -> TokenStream {
+    match arms {
+        [arm] => arm.clone(),
+        [arm, arm_null] => quote! {
+            match expected_version {
+                ::std::option::Option::Some(_) => #arm,
+                ::std::option::Option::None => #arm_null,
+            }
+        },
+        _ => unreachable!("one filter or two for a nullable version"),
+    }
+}
+
+///
+/// Path of diesel re-exported by diesel_versioning, so the generated code doesn't depend on the
+/// imports and dependencies of the deriving crate.
+///
+pub(crate) fn diesel_path(model: &Model) -> TokenStream {
+    let krate = model.krate();
+    quote!(#krate::__private::diesel)
+}
+
+///
+/// Path of diesel-async re-exported by diesel_versioning.
+///
+pub(crate) fn diesel_async_path(model: &Model) -> TokenStream {
+    let krate = model.krate();
+    quote!(#krate::__private::diesel_async)
+}
+
+///
+/// Generics of the struct extended by the connection and, if generic, the backend.
+///
+pub(crate) fn impl_generics(item: &DeriveInput, model: &Model, generic_backend: bool) -> Generics {
+    let diesel = diesel_path(model);
+    let mut generics = item.generics.clone();
+    generics.params.push(parse_quote!(CONN));
+    if generic_backend {
+        generics.params.push(parse_quote!(DB));
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(DB: #diesel::backend::Backend));
+    }
+    generics
+}
+
+///
+/// Implements the statements of the entity for a reference to it. The impls of `Versioned` only
+/// depend on this trait, so a generic backend impl doesn't need to name the statement types in
+/// higher-ranked bounds, which the compiler is not able to normalize.
+///
+fn derive_statements(item: &DeriveInput, model: &Model, impl_async: bool) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    let table_name = model.table_name();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = item.generics.clone();
+    generics.params.insert(0, parse_quote!('query));
+    generics.params.push(parse_quote!(CONN));
+    let version = version_bind_type(model, table_name);
+    let filters: Vec<_> = version_filters(model)
+        .into_iter()
+        .map(|(filter, _)| filter)
+        .collect();
+    let update_queries = filters.iter().map(|filter| {
+        quote! {
+            #diesel::dsl::Filter<
+                #diesel::dsl::Update<&'query #struct_name #ty_generics, &'query #struct_name #ty_generics>,
+                #filter,
+            >
+        }
+    });
+    let delete_queries = filters.iter().map(|filter| {
+        quote! {
+            #diesel::dsl::Filter<
+                #diesel::query_builder::DeleteStatement<
+                    <&'query #struct_name #ty_generics as #diesel::associations::HasTable>::Table,
+                    <&'query #struct_name #ty_generics as #diesel::query_builder::IntoUpdateTarget>::WhereClause,
+                >,
+                #filter,
+            >
+        }
+    });
+    let touch_queries = filters.iter().map(|filter| {
+        quote! {
+            #diesel::dsl::Filter<
+                #diesel::dsl::Update<
+                    &'query #struct_name #ty_generics,
+                    #diesel::dsl::Eq<#table_name::#version_column, #version>,
+                >,
+                #filter,
+            >
+        }
+    });
+    let guard = soft_delete_filter(model, false);
+    let queries: Vec<_> = update_queries
+        .chain(delete_queries)
+        .chain(touch_queries)
+        .map(|query| match &guard {
+            Some((guard_type, _)) => quote!(#diesel::dsl::Filter<#query, #guard_type>),
+            None => query,
+        })
+        .collect();
+    let where_clause = generics.make_where_clause();
+    // the changeset is named through this bound, so a changeset rejected by diesel, e.g. of a version
+    // on a column of another type, isn't reported again by every statement
+    where_clause.predicates.push(parse_quote!(
+        &'query #struct_name #ty_generics: #diesel::AsChangeset<Target = #table_name::table>
+    ));
+    if !impl_async {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel::Connection));
+        for query in &queries {
+            where_clause.predicates.push(parse_quote!(
+                #query: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+            ));
+        }
+        let execute = quote!(#diesel::RunQueryDsl::execute);
+        let update = filtered_execute(
+            model,
+            quote!(#diesel::update(self).set(self)),
+            &guard,
+            &execute,
+        );
+        let delete = filtered_execute(model, quote!(#diesel::delete(self)), &guard, &execute);
+        let touch = filtered_execute(model, touch_statement(model), &guard, &execute);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::VersionedStatements<CONN, #version_type>
+                for &'query #struct_name #ty_generics
+            #where_clause
+            {
+                fn update_with_version(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #update
+                }
+
+                fn delete_with_version(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #delete
+                }
+
+                fn touch_with_version(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #touch
                 }
             }
-            else {
-            #[cfg(not(feature = "async"))]{
-abort_call_site!("missing async feature to use VersionedAsync");
+        }
+    } else {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel_async::AsyncConnection + Send));
+        for query in &queries {
+            where_clause.predicates.push(parse_quote!(
+                #query: #diesel_async::methods::ExecuteDsl<CONN>
+            ));
+        }
+        let execute = quote!(#diesel_async::RunQueryDsl::execute);
+        let update = filtered_execute(
+            model,
+            quote!(#diesel::update(self).set(self)),
+            &guard,
+            &execute,
+        );
+        let delete = filtered_execute(model, quote!(#diesel::delete(self)), &guard, &execute);
+        let touch = filtered_execute(model, touch_statement(model), &guard, &execute);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::VersionedStatementsAsync<'query, CONN, #version_type>
+                for &'query #struct_name #ty_generics
+            #where_clause
+            {
+                fn update_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #update
+                }
+
+                fn delete_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #delete
+                }
+
+                fn touch_with_version<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #touch
                 }
+            }
+        }
+    }
+}
+
+///
+/// Soft delete column as type and as expression. It is named through the check of the column, so a
+/// wrong column isn't reported again by every statement. `None` without a soft delete column.
+///
+pub(crate) fn soft_delete_column(model: &Model) -> Option<(TokenStream, TokenStream)> {
+    let krate = model.krate();
+    let table_name = model.table_name();
+    let column = model.version_field().soft_delete()?;
+    Some((
+        quote!(#krate::__private::SoftDeleteColumn<#table_name::#column>),
+        quote!(#krate::__private::soft_delete_column::<#table_name::#column>(#table_name::#column)),
+    ))
+}
+
+///
+/// Filter restricting a statement to the soft deleted rows if `deleted`, else to the other rows, as
+/// type and as expression. `None` without a soft delete column.
+///
+pub(crate) fn soft_delete_filter(
+    model: &Model,
+    deleted: bool,
+) -> Option<(TokenStream, TokenStream)> {
+    let diesel = diesel_path(model);
+    let (column_type, column) = soft_delete_column(model)?;
+    Some(if deleted {
+        (
+            quote!(#diesel::dsl::IsNotNull<#column_type>),
+            quote!(#diesel::ExpressionMethods::is_not_null(#column)),
+        )
+    } else {
+        (
+            quote!(#diesel::dsl::IsNull<#column_type>),
+            quote!(#diesel::ExpressionMethods::is_null(#column)),
+        )
+    })
+}
 
-            #[cfg(feature = "async")]{
-                quote! {
-                    #[automatically_derived]
-                    impl<CONN> VersionedAsync<CONN, #backend> for #struct_name
-                    where
-                        CONN: diesel_async::AsyncConnection<Backend = #backend> + Send,
-                        i32: diesel::serialize::ToSql<diesel::sql_types::Integer, #backend>,
-                    {
-                        async fn update_versioned(&mut self, conn: &mut CONN) -> std::result::Result<(), diesel::result::Error>
-                        {
-                            let expected_version = self.#version_fieldname;
-                            //FIXME: Frank: increment anpassen
-                            self.#version_fieldname += 1;
-                            let q = diesel::update(&*self)
-                                .set(&*self)
-                                .filter(#table_name::#version_fieldname.eq(expected_version));
-                            let updated_rows = diesel_async::RunQueryDsl::execute(q, conn).await?;
-                            if updated_rows != 1 {
-                                return Err(diesel::result::Error::DatabaseError(
-                                    diesel::result::DatabaseErrorKind::CheckViolation,
-                                    Box::new(format!(
-                                        "optimistic locking: updated {} rows. expected 1",
-                                        updated_rows
-                                    )),
-                                ));
-                            }
-                            Ok(())
-                        }
-
-                        async fn delete_versioned(&mut self, conn: &mut CONN) -> std::result::Result<(), diesel::result::Error>
-                        {
-                            let expected_version = self.#version_fieldname;
-                            let q = diesel::delete(&*self).filter(#table_name::#version_fieldname.eq(expected_version));
-                            let updated_rows = diesel_async::RunQueryDsl::execute(q, conn).await?;
-                            if updated_rows != 1 {
-                                return Err(diesel::result::Error::DatabaseError(
-                                    diesel::result::DatabaseErrorKind::CheckViolation,
-                                    Box::new(format!(
-                                        "optimistic locking: deleted {} rows. expected 1",
-                                        updated_rows
-                                    )),
-                                ));
-                            }
-                            Ok(())
-                        }
-
-                    }
-                } }
-            } 
+///
+/// Executes the statement restricted to `expected_version` and `guard`. A nullable version which
+/// was never set is matched by `IS NULL`.
+///
+pub(crate) fn filtered_execute(
+    model: &Model,
+    statement: TokenStream,
+    guard: &Option<(TokenStream, TokenStream)>,
+    execute: &TokenStream,
+) -> TokenStream {
+    let guard = guard.as_ref().map(|(_, guard)| quote!(.filter(#guard)));
+    let arms: Vec<_> = version_filters(model)
+        .into_iter()
+        .map(|(_, filter)| {
+            quote! {{
+                let q = #statement.filter(#filter)#guard;
+                #execute(q, conn)
+            }}
         })
         .collect();
+    match_expected_version(&arms)
+}
 
-    Ok(quote! {
-        #(#code)*
-    })
+///
+/// Update statement only setting the version column to `version`.
+///
+fn touch_statement(model: &Model) -> TokenStream {
+    let diesel = diesel_path(model);
+    let column = version_column_expression(model, model.table_name());
+    let version = version_bind(model, model.table_name(), quote!(version));
+    quote! {
+        #diesel::update(self).set(#diesel::ExpressionMethods::eq(#column, #version))
+    }
+}
+
+///
+/// Wraps the write of a statement into a transaction, which copies the row image of
+/// `expected_version` into the history table before and inserts the event of `operation` resulting
+/// in `version` into the outbox after, if the entity has a history table or an outbox.
+///
+pub(crate) fn in_transaction(
+    model: &Model,
+    write: TokenStream,
+    operation: &str,
+    version: TokenStream,
+    impl_async: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel_async = diesel_async_path(model);
+    let history = model.version_field().history().is_some();
+    let outbox = model.version_field().outbox().is_some();
+    if !history && !outbox {
+        return write;
+    }
+    if !impl_async {
+        let copy = if history {
+            quote!(|conn| #krate::__private::HistoryStatements::copy_with_version(&*self, expected_version, conn))
+        } else {
+            quote!(|_| ::std::result::Result::Ok(0))
+        };
+        let publish = if outbox {
+            quote!(|conn| #krate::__private::OutboxStatements::publish(&*self, #operation, #version, conn))
+        } else {
+            quote!(|_| ::std::result::Result::Ok(0))
+        };
+        quote! {
+            #krate::__private::write_in_transaction(conn, #copy, |conn| #write, #publish)
+        }
+    } else {
+        let boxed = quote!(#diesel_async::scoped_futures::ScopedFutureExt::scope_boxed);
+        let skipped = quote!(|_| #boxed(async { ::std::result::Result::Ok(0) }));
+        let copy = if history {
+            quote!(|conn| #boxed(#krate::__private::HistoryStatementsAsync::copy_with_version(&*self, expected_version, conn)))
+        } else {
+            skipped.clone()
+        };
+        let publish = if outbox {
+            quote!(|conn| #boxed(#krate::__private::OutboxStatementsAsync::publish(&*self, #operation, #version, conn)))
+        } else {
+            skipped
+        };
+        quote! {
+            #krate::__private::write_in_transaction_async(conn, #copy, |conn| #boxed(#write), #publish)
+        }
+    }
+}
+
+///
+/// Version following `expected_version`, without setting the version field.
+///
+fn following_version(model: &Model) -> TokenStream {
+    if model.version_field().nullable() {
+        let initial = match model.version_field().initial() {
+            Some(initial) => quote!(#initial),
+            None => quote!(1),
+        };
+        quote! {
+            ::std::option::Option::Some(match expected_version {
+                ::std::option::Option::Some(version) => version + 1,
+                ::std::option::Option::None => #initial,
+            })
+        }
+    } else {
+        //FIXME: Frank: increment anpassen
+        quote!(expected_version + 1)
+    }
+}
+
+///
+/// Sets the version field to the version following `expected_version`.
+///
+pub(crate) fn next_version(model: &Model) -> TokenStream {
+    let version_member = model.version_field().member();
+    let following_version = following_version(model);
+    quote! {
+        self.#version_member = #following_version;
+    }
+}
+
+///
+/// Reports a soft deleted row as deleted instead of as version conflict, if the entity has a soft
+/// delete column.
+///
+fn deleted_conflict(model: &Model, impl_async: bool) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    if model.version_field().soft_delete().is_none() {
+        return quote!();
+    }
+    let load_deleted = if impl_async {
+        quote!(#krate::__private::SoftDeleteStatementsAsync::load_deleted(&*self, conn).await)
+    } else {
+        quote!(#krate::__private::SoftDeleteStatements::load_deleted(&*self, conn))
+    };
+    quote! {
+        if #load_deleted? {
+            return ::std::result::Result::Err(#krate::__private::deleted_conflict(
+                #diesel::Identifiable::id(&*self),
+            ));
+        }
+    }
+}
+
+///
+/// Body of a versioned write by the statement `write`, which sets the next version and writes it
+/// within the transaction of `in_transaction`. The version is restored if not exactly one row was
+/// written, which is reported as conflict of `verb`, or of a soft deleted row if `check_deleted`.
+/// `written` is run after a successful write.
+///
+pub(crate) fn versioned_write(
+    model: &Model,
+    write: TokenStream,
+    operation: &str,
+    verb: &str,
+    check_deleted: bool,
+    written: TokenStream,
+    impl_async: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let version_member = model.version_field().member();
+    let next_version = next_version(model);
+    let deleted = if check_deleted {
+        deleted_conflict(model, impl_async)
+    } else {
+        quote!()
+    };
+    let write = in_transaction(
+        model,
+        write,
+        operation,
+        quote!(self.#version_member),
+        impl_async,
+    );
+    let write = if impl_async {
+        quote!(#write.await)
+    } else {
+        write
+    };
+    quote! {
+        let expected_version = self.#version_member;
+        #next_version
+        let updated_rows = #write;
+        let updated_rows = match updated_rows {
+            ::std::result::Result::Ok(updated_rows) => updated_rows,
+            ::std::result::Result::Err(err) => {
+                self.#version_member = expected_version;
+                return ::std::result::Result::Err(err);
+            }
+        };
+        if updated_rows != 1 {
+            self.#version_member = expected_version;
+            #deleted
+            return ::std::result::Result::Err(#krate::__private::version_conflict(
+                #verb,
+                updated_rows,
+                #diesel::Identifiable::id(&*self),
+            ));
+        }
+        #written
+        ::std::result::Result::Ok(())
+    }
+}
+
+fn derive_versioned(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel::Connection<Backend = #backend>
+    ));
+    // also bounded for a concrete backend, so the statements are only checked where the entity is
+    // used, and a wrong version column is reported by the check of the column alone
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::VersionedStatements<CONN, #version_type>
+    ));
+    if model.version_field().history().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::HistoryStatements<CONN, #version_type, Self>
+        ));
+    }
+    if model.version_field().soft_delete().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::SoftDeleteStatements<CONN, #version_type>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatements<CONN, #version_type>
+        ));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let update = versioned_write(
+        model,
+        quote!(#krate::__private::VersionedStatements::update_with_version(&*self, expected_version, conn)),
+        "update",
+        "updated",
+        true,
+        quote!(),
+        false,
+    );
+    let touch = versioned_write(
+        model,
+        quote!(#krate::__private::VersionedStatements::touch_with_version(&*self, expected_version, self.#version_member, conn)),
+        "touch",
+        "touched",
+        true,
+        quote!(),
+        false,
+    );
+    let delete_body = match model.version_field().soft_delete() {
+        // the row is kept as tombstone of a new version
+        Some(_) => versioned_write(
+            model,
+            quote!(#krate::__private::SoftDeleteStatements::delete_with_version(&*self, expected_version, self.#version_member, conn)),
+            "delete",
+            "deleted",
+            true,
+            quote!(),
+            false,
+        ),
+        None => {
+            let delete = in_transaction(
+                model,
+                quote!(#krate::__private::VersionedStatements::delete_with_version(&*self, expected_version, conn)),
+                "delete",
+                following_version(model),
+                false,
+            );
+            quote! {
+                let expected_version = self.#version_member;
+                let updated_rows = #delete?;
+                if updated_rows != 1 {
+                    return ::std::result::Result::Err(#krate::__private::version_conflict(
+                        "deleted",
+                        updated_rows,
+                        #diesel::Identifiable::id(&*self),
+                    ));
+                }
+                ::std::result::Result::Ok(())
+            }
+        }
+    };
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::Versioned<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            fn update_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #update
+            }
+
+            fn touch_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #touch
+            }
+
+            fn delete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #delete_body
+            }
+        }
+    }
+}
+
+fn derive_versioned_async(
+    item: &DeriveInput,
+    model: &Model,
+    backend: &TokenStream,
+    generic_backend: bool,
+) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+    let version_type = model.version_field().ty();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, generic_backend);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel_async::AsyncConnection<Backend = #backend> + Send
+    ));
+    // the futures hold `&mut self`, so they are only Send for a Send entity. Bounding `Self`
+    // directly would shadow the auto impl and fail for entities with lifetimes.
+    for param in item.generics.type_params() {
+        let ident = &param.ident;
+        where_clause.predicates.push(parse_quote!(#ident: Send));
+    }
+    // also bounded for a concrete backend, so the statements are only checked where the entity is
+    // used, and a wrong version column is reported by the check of the column alone
+    where_clause.predicates.push(parse_quote!(
+        for<'query> &'query Self: #krate::__private::VersionedStatementsAsync<'query, CONN, #version_type>
+    ));
+    if model.version_field().history().is_some() || model.version_field().outbox().is_some() {
+        // the statements within the transaction share the entity
+        for param in item.generics.type_params() {
+            let ident = &param.ident;
+            where_clause.predicates.push(parse_quote!(#ident: Sync));
+        }
+    }
+    if model.version_field().history().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::HistoryStatementsAsync<'query, CONN, #version_type, Self>
+        ));
+    }
+    if model.version_field().soft_delete().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::SoftDeleteStatementsAsync<'query, CONN, #version_type>
+        ));
+    }
+    if model.version_field().outbox().is_some() {
+        where_clause.predicates.push(parse_quote!(
+            for<'query> &'query Self: #krate::__private::OutboxStatementsAsync<'query, CONN, #version_type>
+        ));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let update = versioned_write(
+        model,
+        quote!(#krate::__private::VersionedStatementsAsync::update_with_version(&*self, expected_version, conn)),
+        "update",
+        "updated",
+        true,
+        quote!(),
+        true,
+    );
+    let touch = versioned_write(
+        model,
+        quote!(#krate::__private::VersionedStatementsAsync::touch_with_version(&*self, expected_version, self.#version_member, conn)),
+        "touch",
+        "touched",
+        true,
+        quote!(),
+        true,
+    );
+    let delete_body = match model.version_field().soft_delete() {
+        // the row is kept as tombstone of a new version
+        Some(_) => versioned_write(
+            model,
+            quote!(#krate::__private::SoftDeleteStatementsAsync::delete_with_version(&*self, expected_version, self.#version_member, conn)),
+            "delete",
+            "deleted",
+            true,
+            quote!(),
+            true,
+        ),
+        None => {
+            let delete = in_transaction(
+                model,
+                quote!(#krate::__private::VersionedStatementsAsync::delete_with_version(&*self, expected_version, conn)),
+                "delete",
+                following_version(model),
+                true,
+            );
+            quote! {
+                let expected_version = self.#version_member;
+                let updated_rows = #delete.await?;
+                if updated_rows != 1 {
+                    return ::std::result::Result::Err(#krate::__private::version_conflict(
+                        "deleted",
+                        updated_rows,
+                        #diesel::Identifiable::id(&*self),
+                    ));
+                }
+                ::std::result::Result::Ok(())
+            }
+        }
+    };
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::VersionedAsync<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            async fn update_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #update
+            }
+
+            async fn touch_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #touch
+            }
+
+            async fn delete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #delete_body
+            }
+        }
+    }
 }
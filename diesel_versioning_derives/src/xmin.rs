@@ -0,0 +1,357 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse_quote};
+
+use crate::model::Model;
+use crate::versioned::{check_version_column, diesel_async_path, diesel_path, impl_generics};
+
+///
+/// Implements `Versioned` for an entity using the Postgres system column `xmin` as version. The
+/// column is read back by `RETURNING xmin`, as the new value is chosen by Postgres.
+///
+pub fn derive(item: &DeriveInput, model: &Model) -> TokenStream {
+    let krate = model.krate();
+    let statements = derive_statements(item, model, false);
+    let code = model
+        .backends()
+        .iter()
+        .map(|backend| derive_versioned(item, model, backend));
+    let async_statements = derive_statements(item, model, true);
+    let async_code = model
+        .backends()
+        .iter()
+        .map(|backend| derive_versioned_async(item, model, backend));
+    let check = check_version_column(model);
+    quote! {
+        #check
+        #statements
+        #(#code)*
+        #krate::__private::if_async! {
+            #async_statements
+            #(#async_code)*
+        }
+    }
+}
+
+///
+/// Implements the statements of the entity for a reference to it, like the statements of an
+/// integer version.
+///
+fn derive_statements(item: &DeriveInput, model: &Model, impl_async: bool) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_column = model.version_field().column_name();
+    let version_type = model.version_field().ty();
+    let table_name = model.table_name();
+
+    // all columns but the primary key and xmin, which can't be set
+    let changeset_types = model.changeset().iter().map(|column| {
+        let column_name = column.column_name();
+        let ty = column.ty();
+        quote!(#diesel::dsl::Eq<#table_name::#column_name, &'query #ty>)
+    });
+    let changeset_values = model.changeset().iter().map(|column| {
+        let column_name = column.column_name();
+        let member = column.member();
+        quote!(#diesel::ExpressionMethods::eq(#table_name::#column_name, &self.#member))
+    });
+    // a touch writes a column unchanged, so Postgres creates a new row version
+    let touch_column = model.changeset()[0].column_name();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = item.generics.clone();
+    generics.params.insert(0, parse_quote!('query));
+    generics.params.push(parse_quote!(CONN));
+    let filter = quote!(#diesel::dsl::Eq<#table_name::#version_column, #version_type>);
+    let update_query = quote! {
+        #diesel::dsl::Returning<
+            #diesel::dsl::Filter<
+                #diesel::dsl::Update<&'query #struct_name #ty_generics, (#(#changeset_types,)*)>,
+                #filter,
+            >,
+            #table_name::#version_column,
+        >
+    };
+    let touch_query = quote! {
+        #diesel::dsl::Returning<
+            #diesel::dsl::Filter<
+                #diesel::dsl::Update<
+                    &'query #struct_name #ty_generics,
+                    #diesel::dsl::Eq<#table_name::#touch_column, #table_name::#touch_column>,
+                >,
+                #filter,
+            >,
+            #table_name::#version_column,
+        >
+    };
+    let delete_query = quote! {
+        #diesel::dsl::Filter<
+            #diesel::query_builder::DeleteStatement<
+                <&'query #struct_name #ty_generics as #diesel::associations::HasTable>::Table,
+                <&'query #struct_name #ty_generics as #diesel::query_builder::IntoUpdateTarget>::WhereClause,
+            >,
+            #filter,
+        >
+    };
+    let update = quote! {
+        #diesel::update(self)
+            .set((#(#changeset_values,)*))
+            .filter(#diesel::ExpressionMethods::eq(#table_name::#version_column, expected_version))
+            .returning(#table_name::#version_column)
+    };
+    let touch = quote! {
+        #diesel::update(self)
+            .set(#diesel::ExpressionMethods::eq(#table_name::#touch_column, #table_name::#touch_column))
+            .filter(#diesel::ExpressionMethods::eq(#table_name::#version_column, expected_version))
+            .returning(#table_name::#version_column)
+    };
+    let delete = quote! {
+        #diesel::delete(self)
+            .filter(#diesel::ExpressionMethods::eq(#table_name::#version_column, expected_version))
+    };
+    let where_clause = generics.make_where_clause();
+    if !impl_async {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel::Connection));
+        where_clause.predicates.push(parse_quote!(
+            #update_query: #diesel::query_dsl::LoadQuery<'query, CONN, #version_type>
+        ));
+        where_clause.predicates.push(parse_quote!(
+            #touch_query: #diesel::query_dsl::LoadQuery<'query, CONN, #version_type>
+        ));
+        where_clause.predicates.push(parse_quote!(
+            #delete_query: #diesel::query_dsl::methods::ExecuteDsl<CONN>
+        ));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::XminStatements<CONN, #version_type>
+                for &'query #struct_name #ty_generics
+            #where_clause
+            {
+                fn update_with_xmin(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<#version_type> {
+                    #diesel::RunQueryDsl::get_result(#update, conn)
+                }
+
+                fn delete_with_xmin(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<usize> {
+                    #diesel::RunQueryDsl::execute(#delete, conn)
+                }
+
+                fn touch_with_xmin(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> #diesel::QueryResult<#version_type> {
+                    #diesel::RunQueryDsl::get_result(#touch, conn)
+                }
+            }
+        }
+    } else {
+        where_clause
+            .predicates
+            .push(parse_quote!(CONN: #diesel_async::AsyncConnection + Send));
+        where_clause.predicates.push(parse_quote!(
+            #update_query: #diesel_async::methods::LoadQuery<'query, CONN, #version_type>
+        ));
+        where_clause.predicates.push(parse_quote!(
+            #touch_query: #diesel_async::methods::LoadQuery<'query, CONN, #version_type>
+        ));
+        where_clause.predicates.push(parse_quote!(
+            #delete_query: #diesel_async::methods::ExecuteDsl<CONN>
+        ));
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics #krate::__private::XminStatementsAsync<'query, CONN, #version_type>
+                for &'query #struct_name #ty_generics
+            #where_clause
+            {
+                fn update_with_xmin(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<#version_type>> + Send {
+                    #diesel_async::RunQueryDsl::get_result(#update, conn)
+                }
+
+                fn delete_with_xmin<'conn>(
+                    self,
+                    expected_version: #version_type,
+                    conn: &'conn mut CONN,
+                ) -> CONN::ExecuteFuture<'conn, 'query> {
+                    #diesel_async::RunQueryDsl::execute(#delete, conn)
+                }
+
+                fn touch_with_xmin(
+                    self,
+                    expected_version: #version_type,
+                    conn: &mut CONN,
+                ) -> impl ::std::future::Future<Output = #diesel::QueryResult<#version_type>> + Send {
+                    #diesel_async::RunQueryDsl::get_result(#touch, conn)
+                }
+            }
+        }
+    }
+}
+
+///
+/// Maps the result of an update returning the new xmin. No returned row means, that the row was
+/// changed or deleted since it was read.
+///
+fn set_returned_version(model: &Model, result: TokenStream, operation: &str) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let version_member = model.version_field().member();
+    quote! {
+        match #result {
+            ::std::result::Result::Ok(version) => {
+                self.#version_member = version;
+                ::std::result::Result::Ok(())
+            }
+            ::std::result::Result::Err(#diesel::result::Error::NotFound) => {
+                ::std::result::Result::Err(#krate::__private::version_conflict(
+                    #operation,
+                    0,
+                    #diesel::Identifiable::id(&*self),
+                ))
+            }
+            ::std::result::Result::Err(err) => ::std::result::Result::Err(err),
+        }
+    }
+}
+
+///
+/// Checks, that a delete removed exactly one row.
+///
+fn check_deleted(model: &Model, deleted_rows: TokenStream) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    quote! {
+        let deleted_rows = #deleted_rows;
+        if deleted_rows != 1 {
+            return ::std::result::Result::Err(#krate::__private::version_conflict(
+                "deleted",
+                deleted_rows,
+                #diesel::Identifiable::id(&*self),
+            ));
+        }
+        ::std::result::Result::Ok(())
+    }
+}
+
+fn derive_versioned(item: &DeriveInput, model: &Model, backend: &syn::Path) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, false);
+    // the new xmin is loaded by RETURNING
+    generics.make_where_clause().predicates.push(parse_quote!(
+        CONN: #diesel::connection::LoadConnection<Backend = #backend>
+    ));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let update = set_returned_version(
+        model,
+        quote!(#krate::__private::XminStatements::update_with_xmin(&*self, self.#version_member, conn)),
+        "updated",
+    );
+    let touch = set_returned_version(
+        model,
+        quote!(#krate::__private::XminStatements::touch_with_xmin(&*self, self.#version_member, conn)),
+        "touched",
+    );
+    let delete = check_deleted(
+        model,
+        quote!(#krate::__private::XminStatements::delete_with_xmin(&*self, self.#version_member, conn)?),
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::Versioned<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            fn update_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #update
+            }
+
+            fn touch_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #touch
+            }
+
+            fn delete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #delete
+            }
+        }
+    }
+}
+
+fn derive_versioned_async(item: &DeriveInput, model: &Model, backend: &syn::Path) -> TokenStream {
+    let krate = model.krate();
+    let diesel = diesel_path(model);
+    let diesel_async = diesel_async_path(model);
+    let struct_name = &item.ident;
+    let version_member = model.version_field().member();
+
+    let (_, ty_generics, _) = item.generics.split_for_impl();
+    let mut generics = impl_generics(item, model, false);
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(parse_quote!(
+        CONN: #diesel_async::AsyncConnection<Backend = #backend> + Send
+    ));
+    // see derive_versioned_async of an integer version
+    for param in item.generics.type_params() {
+        let ident = &param.ident;
+        where_clause.predicates.push(parse_quote!(#ident: Send));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let update = set_returned_version(
+        model,
+        quote!(#krate::__private::XminStatementsAsync::update_with_xmin(&*self, self.#version_member, conn).await),
+        "updated",
+    );
+    let touch = set_returned_version(
+        model,
+        quote!(#krate::__private::XminStatementsAsync::touch_with_xmin(&*self, self.#version_member, conn).await),
+        "touched",
+    );
+    let delete = check_deleted(
+        model,
+        quote!(#krate::__private::XminStatementsAsync::delete_with_xmin(&*self, self.#version_member, conn).await?),
+    );
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #krate::VersionedAsync<CONN, #backend> for #struct_name #ty_generics
+        #where_clause
+        {
+            async fn update_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #update
+            }
+
+            async fn touch_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #touch
+            }
+
+            async fn delete_versioned(&mut self, conn: &mut CONN) -> ::std::result::Result<(), #diesel::result::Error>
+            {
+                #delete
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Lists the tables of a SQLite or Postgres database, whose version column is missing, nullable or
+//! without default, see `diesel_versioning::lint::VersionLint`.
+//!
+//! ```text
+//! diesel_versioning_lint <database-url> [--column <name>] [--ignore <table>]...
+//! ```
+//!
+//! A `postgres://` or `postgresql://` url is audited as Postgres, any other as path of an existing
+//! SQLite database. Exits with 1 if a table has an issue and with 2 on an error, e.g. if there is no
+//! database at the path.
+
+use std::{env, path::Path, process::ExitCode};
+
+use diesel::{Connection, PgConnection, SqliteConnection};
+use diesel_versioning::lint::{LintFinding, VersionLint};
+
+const USAGE: &str =
+    "usage: diesel_versioning_lint <database-url> [--column <name>] [--ignore <table>]...";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(findings) if findings.is_empty() => ExitCode::SUCCESS,
+        Ok(findings) => {
+            for finding in findings {
+                println!("{finding}");
+            }
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<Vec<LintFinding>, Box<dyn std::error::Error>> {
+    let mut database_url = None;
+    let mut column = "version";
+    let mut ignored = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--column" => column = args.next().ok_or(USAGE)?,
+            "--ignore" => ignored.push(args.next().ok_or(USAGE)?.as_str()),
+            _ if database_url.is_none() && !arg.starts_with("--") => database_url = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let database_url = database_url.ok_or(USAGE)?;
+
+    let lint = ignored
+        .into_iter()
+        .fold(VersionLint::new(column), VersionLint::ignore);
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        let mut conn = PgConnection::establish(database_url)?;
+        Ok(lint.postgres(&mut conn)?)
+    } else {
+        // SQLite would create an empty database, which has no issues to report
+        if !Path::new(database_url).is_file() {
+            return Err(format!("no SQLite database at {database_url}").into());
+        }
+        let mut conn = SqliteConnection::establish(database_url)?;
+        Ok(lint.sqlite(&mut conn)?)
+    }
+}
@@ -0,0 +1,5 @@
+CREATE TABLE simple (
+    id INTEGER NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
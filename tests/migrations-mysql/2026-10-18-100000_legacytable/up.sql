@@ -0,0 +1,5 @@
+CREATE TABLE legacy (
+    id INTEGER NOT NULL PRIMARY KEY,
+    version INTEGER NULL,
+    body TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE document (
+    id INTEGER NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    version INTEGER NOT NULL DEFAULT 1,
+    body TEXT NOT NULL,
+    deleted_at TIMESTAMP NULL
+);
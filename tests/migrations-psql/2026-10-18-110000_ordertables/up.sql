@@ -0,0 +1,12 @@
+CREATE TABLE orders (
+    id INTEGER NOT NULL PRIMARY KEY,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
+
+CREATE TABLE order_lines (
+    id INTEGER NOT NULL PRIMARY KEY,
+    order_id INTEGER NOT NULL REFERENCES orders (id),
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE TABLE adopted (
+    id INTEGER NOT NULL PRIMARY KEY,
+    body TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE document (
+    id SERIAL PRIMARY KEY,
+    version INTEGER NOT NULL DEFAULT 1,
+    body TEXT NOT NULL,
+    deleted_at TIMESTAMP
+);
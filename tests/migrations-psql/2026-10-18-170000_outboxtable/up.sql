@@ -0,0 +1,8 @@
+CREATE TABLE outbox (
+    id SERIAL PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    operation TEXT NOT NULL,
+    payload TEXT NOT NULL
+);
@@ -0,0 +1,13 @@
+CREATE TABLE note (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    version INTEGER NOT NULL DEFAULT 1,
+    body TEXT NOT NULL,
+    remark TEXT
+);
+CREATE TABLE note_history (
+    id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    remark TEXT,
+    PRIMARY KEY (id, version)
+);
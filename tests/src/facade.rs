@@ -0,0 +1,26 @@
+//! Entity deriving `Versioned` through a re-export of diesel_versioning, without importing any
+//! diesel or diesel_versioning items.
+
+/// Facade re-exporting diesel_versioning, like a crate wrapping it would do.
+pub mod versioning {
+    pub use diesel_versioning::*;
+}
+
+#[derive(
+    diesel::Queryable,
+    diesel::Selectable,
+    diesel::AsChangeset,
+    diesel::Identifiable,
+    Debug,
+    PartialEq,
+    Clone,
+    versioning::Versioned,
+)]
+#[diesel(table_name = crate::schema::simple)]
+#[diesel_versioning(crate = "crate::facade::versioning")]
+pub struct FacadeEntry {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
@@ -1,2 +1,3 @@
+pub mod facade;
 pub mod model;
 pub mod schema;
@@ -1,15 +1,22 @@
+use std::{borrow::Cow, fmt::Debug, marker::PhantomData};
+
 use diesel::prelude::*;
-#[cfg(feature = "sync")]
-use diesel_versioning::Versioned;
-#[cfg(feature = "async")]
-use diesel_versioning::VersionedAsync;
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+};
+use diesel_versioning::{Versioned, pg::TransactionId};
+use serde::Serialize;
 
 use crate::schema::{self};
 
-#[cfg(feature = "sync")]
 #[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
 #[diesel(table_name = schema::simple)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+#[cfg_attr(feature = "mysql", diesel(check_for_backend(diesel::mysql::Mysql)))]
 pub struct SimpleEntry {
     pub id: i32,
     #[version]
@@ -17,19 +24,239 @@ pub struct SimpleEntry {
     pub body: String,
 }
 
-#[cfg(feature = "async")]
-#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, VersionedAsync)]
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
 #[diesel(table_name = schema::simple)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
-pub struct SimpleEntry {
+pub struct SimpleTupleEntry(
+    #[diesel(column_name = id)] pub i32,
+    #[diesel(column_name = version)]
+    #[version]
+    pub i32,
+    #[diesel(column_name = body)] pub String,
+);
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::simple)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct BorrowedEntry<'a> {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: Cow<'a, str>,
+}
+
+/// Text of a body, whose kind is only known to the type, e.g. [`Markdown`].
+#[derive(AsExpression, FromSqlRow, Debug, PartialEq, Clone, Serialize)]
+#[diesel(sql_type = Text)]
+#[serde(transparent)]
+pub struct Body<K>(pub String, #[serde(skip)] pub PhantomData<K>);
+
+impl<K> Body<K> {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self(text.into(), PhantomData)
+    }
+}
+
+impl<K: Debug, DB: Backend> ToSql<Text, DB> for Body<K>
+where
+    String: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+impl<K, DB: Backend> FromSql<Text, DB> for Body<K>
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        String::from_sql(bytes).map(Self::new)
+    }
+}
+
+/// Kind of a [`Body`].
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Markdown;
+
+/// Generic over the kind of its body, so the derive has to bound the type parameter.
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::simple)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct GenericEntry<K>
+{
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: Body<K>,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::simple)]
+pub struct AnyBackendEntry {
     pub id: i32,
     #[version]
     pub version: i32,
     pub body: String,
 }
 
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::simple)]
+pub struct AuditedEntry {
+    pub id: i32,
+    #[version(history = schema::simple_history)]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned, Serialize)]
+#[diesel(table_name = schema::simple)]
+pub struct PublishedEntry {
+    pub id: i32,
+    #[version(outbox = schema::outbox)]
+    pub version: i32,
+    pub body: String,
+}
+
+/// Generic entity with a history table and an outbox, whose async writes require a `Sync` kind.
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned, Serialize)]
+#[diesel(table_name = schema::simple)]
+pub struct AuditedGenericEntry<K>
+{
+    pub id: i32,
+    #[version(history = schema::simple_history, outbox = schema::outbox)]
+    pub version: i32,
+    pub body: Body<K>,
+}
+
+#[derive(Queryable, Selectable, Debug, PartialEq)]
+#[diesel(table_name = schema::outbox)]
+pub struct OutboxEvent {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub version: i64,
+    pub operation: String,
+    pub payload: String,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::note)]
+pub struct Note {
+    pub id: i32,
+    #[version(history = schema::note_history)]
+    pub version: i32,
+    pub body: String,
+    pub remark: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = schema::note)]
+pub struct NewNote {
+    pub body: String,
+    pub remark: Option<String>,
+}
+
 #[derive(Insertable, Debug)]
 #[diesel(table_name = schema::simple)]
 pub struct NewSimpleEntry {
     pub body: String,
 }
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::document)]
+pub struct Document {
+    pub id: i32,
+    #[version(soft_delete = deleted_at)]
+    pub version: i32,
+    pub body: String,
+}
+
+/// Generic soft deleted entity, whose async writes require a `Sync` kind.
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::document)]
+pub struct GenericDocument<K>
+{
+    pub id: i32,
+    #[version(soft_delete = deleted_at)]
+    pub version: i32,
+    pub body: Body<K>,
+}
+
+/// Maps the soft delete column, as text of SQLite.
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::document)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct DeletableDocument {
+    pub id: i32,
+    #[version(soft_delete = deleted_at)]
+    pub version: i32,
+    pub body: String,
+    pub deleted_at: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = schema::document)]
+pub struct NewDocument {
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::tenant, primary_key(tenant_id, id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+#[cfg_attr(feature = "mysql", diesel(check_for_backend(diesel::mysql::Mysql)))]
+pub struct TenantEntry {
+    pub tenant_id: i32,
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::legacy)]
+pub struct LegacyEntry {
+    pub id: i32,
+    #[version(initial = 100)]
+    pub version: Option<i32>,
+    pub body: String,
+}
+
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::orders)]
+pub struct Order {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Associations, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::order_lines, belongs_to(Order))]
+pub struct OrderLine {
+    pub id: i32,
+    pub order_id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::adopted)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+#[cfg_attr(feature = "mysql", diesel(check_for_backend(diesel::mysql::Mysql)))]
+pub struct AdoptedEntry {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Queryable, Selectable, AsChangeset, Debug, PartialEq, Identifiable, Clone, Versioned)]
+#[diesel(table_name = schema::plain)]
+pub struct PlainEntry {
+    pub id: i32,
+    pub body: String,
+    #[version(xmin)]
+    pub xmin: TransactionId,
+}
@@ -1,4 +1,92 @@
-// @generated automatically by Diesel CLI.
+// Generated by Diesel CLI and edited by hand: `adopted.version` is added by the tests of
+// `AddVersionColumn` and `plain.xmin` is a system column of Postgres, so no migration creates them.
+
+// the version is added by the tests of diesel_versioning::migration::AddVersionColumn
+diesel::table! {
+    adopted (id) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+    }
+}
+
+diesel::table! {
+    /// Soft deleted rows are marked by `deleted_at`.
+    document (id) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    legacy (id) {
+        id -> Integer,
+        version -> Nullable<Integer>,
+        body -> Text,
+    }
+}
+
+diesel::table! {
+    /// Versions of `note` with a nullable column.
+    note (id) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+        remark -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    note_history (id, version) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+        remark -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    order_lines (id) {
+        id -> Integer,
+        order_id -> Integer,
+        version -> Integer,
+        body -> Text,
+    }
+}
+
+diesel::table! {
+    orders (id) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+    }
+}
+
+diesel::table! {
+    /// Events of the versioned writes of `PublishedEntry`.
+    outbox (id) {
+        id -> Integer,
+        entity_type -> Text,
+        entity_id -> Text,
+        version -> BigInt,
+        operation -> Text,
+        payload -> Text,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_versioning::pg::sql_types::Xid;
+
+    /// Postgres only table without a version column, versioned by the system column `xmin`.
+    plain (id) {
+        id -> Integer,
+        body -> Text,
+        xmin -> Xid,
+    }
+}
 
 diesel::table! {
     simple (id) {
@@ -7,3 +95,25 @@ diesel::table! {
         body -> Text,
     }
 }
+
+diesel::table! {
+    simple_history (id, version) {
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+        recorded_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tenant (tenant_id, id) {
+        tenant_id -> Integer,
+        id -> Integer,
+        version -> Integer,
+        body -> Text,
+    }
+}
+
+diesel::joinable!(order_lines -> orders (order_id));
+
+diesel::allow_tables_to_appear_in_same_query!(adopted, document, legacy, note, note_history, order_lines, orders, outbox, plain, simple, simple_history, tenant,);
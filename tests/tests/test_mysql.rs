@@ -0,0 +1,1090 @@
+#![cfg(feature = "mysql")]
+//! Tests against a local MySQL/MariaDB server, see `docker-compose.yaml`. The url of the server can
+//! be configured by `MYSQL_DATABASE_URL`.
+
+use std::error::Error;
+
+#[cfg(feature = "sync")]
+use diesel::{MysqlConnection, RunQueryDsl};
+use diesel::{ExpressionMethods, QueryDsl, result::DatabaseErrorKind};
+#[cfg(feature = "async")]
+use diesel_async::{AsyncMysqlConnection, RunQueryDsl};
+#[cfg(feature = "sync")]
+use diesel_versioning::{Versioned, VersionedChild};
+#[cfg(feature = "async")]
+use diesel_versioning::{VersionedAsync, VersionedChildAsync};
+use diesel_migrations::MigrationHarness;
+use diesel_versioning::migration::{AddVersionColumn, SqlMigration};
+use diesel_versioning_async_example::{
+    model::{AdoptedEntry, LegacyEntry, NewSimpleEntry, Order, OrderLine, SimpleEntry, TenantEntry},
+    schema::{self},
+};
+use fixtures::{logger, mysql, mysql_ddl};
+use rstest::rstest;
+
+mod fixtures;
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)?;
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn)?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!("updated text", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)
+        .await?;
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn).await?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!("updated text", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_unchanged_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)?;
+
+    // MySQL reports changed rows unless CLIENT_FOUND_ROWS is set. As the version is always
+    // incremented, an update without other changes still changes the row.
+    first_entry.update_versioned(conn)?;
+    first_entry.update_versioned(conn)?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!("initial text", &entry.body);
+    assert_eq!(3, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_unchanged_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)
+        .await?;
+
+    // MySQL reports changed rows unless CLIENT_FOUND_ROWS is set. As the version is always
+    // incremented, an update without other changes still changes the row.
+    first_entry.update_versioned(conn).await?;
+    first_entry.update_versioned(conn).await?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!("initial text", &entry.body);
+    assert_eq!(3, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_update_an_entry_twice(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn)?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn);
+
+    match result {
+        Ok(_) => panic!("expected error on update"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!("updated text on first", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_update_an_entry_twice(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn).await?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn).await;
+
+    match result {
+        Ok(_) => panic!("expected error on update"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!("updated text on first", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_delete_an_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)?;
+
+    first_entry.delete_versioned(conn)?;
+
+    let count: i64 = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .count()
+        .get_result(conn)?;
+    assert_eq!(0, count);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_delete_an_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)
+        .await?;
+
+    first_entry.delete_versioned(conn).await?;
+
+    let count: i64 = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .count()
+        .get_result(conn)
+        .await?;
+    assert_eq!(0, count);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_delete_a_updated_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn)?;
+
+    let result = second_entry.delete_versioned(conn);
+
+    match result {
+        Ok(_) => panic!("expected error on delete"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!("updated text on first", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_delete_a_updated_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut first_entry = schema::simple::table
+        .order(schema::simple::id.desc())
+        .first::<SimpleEntry>(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn).await?;
+
+    let result = second_entry.delete_versioned(conn).await;
+
+    match result {
+        Ok(_) => panic!("expected error on delete"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!("updated text on first", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry_with_composite_key(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let mut entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&entry)
+        .execute(conn)?;
+    let mut second_entry = entry.clone();
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    let result = second_entry.update_versioned(conn);
+    assert!(result.is_err(), "expected error on update");
+
+    let stored = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_with_composite_key(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let mut entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&entry)
+        .execute(conn)
+        .await?;
+    let mut second_entry = entry.clone();
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    let result = second_entry.update_versioned(conn).await;
+    assert!(result.is_err(), "expected error on update");
+
+    let stored = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_unversioned_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(Some(100), entry.version);
+    entry.update_versioned(conn)?;
+    assert_eq!(Some(101), entry.version);
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_unversioned_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(Some(100), entry.version);
+    entry.update_versioned(conn).await?;
+    assert_eq!(Some(101), entry.version);
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_update_an_unversioned_entry_twice(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+    let mut second_entry = entry.clone();
+    entry.body = "updated text on first".to_owned();
+    entry.update_versioned(conn)?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn);
+    assert!(result.is_err(), "expected error on update");
+    assert_eq!(None, second_entry.version);
+    let result = second_entry.delete_versioned(conn);
+    assert!(result.is_err(), "expected error on delete");
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_update_an_unversioned_entry_twice(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+    let mut second_entry = entry.clone();
+    entry.body = "updated text on first".to_owned();
+    entry.update_versioned(conn).await?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn).await;
+    assert!(result.is_err(), "expected error on update");
+    assert_eq!(None, second_entry.version);
+    let result = second_entry.delete_versioned(conn).await;
+    assert!(result.is_err(), "expected error on delete");
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_delete_an_unversioned_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    use diesel::OptionalExtension;
+
+    let conn = &mut mysql;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+
+    entry.delete_versioned(conn)?;
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .optional()?;
+    assert_eq!(None, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_delete_an_unversioned_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    use diesel::OptionalExtension;
+
+    let conn = &mut mysql.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+
+    entry.delete_versioned(conn).await?;
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await
+        .optional()?;
+    assert_eq!(None, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_touch_an_entry(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    order.body = "unsaved text".to_owned();
+    order.touch_versioned(conn)?;
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!("order", &stored.body);
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_touch_an_entry(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    order.body = "unsaved text".to_owned();
+    order.touch_versioned(conn).await?;
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!("order", &stored.body);
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_a_child_and_touch_its_parent(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    line.body = "updated text".to_owned();
+    line.update_versioned_with_parent(&mut order, conn)?;
+    assert_eq!(2, line.version);
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!(order, stored);
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)?;
+    assert_eq!(line, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_a_child_and_touch_its_parent(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    line.body = "updated text".to_owned();
+    line.update_versioned_with_parent(&mut order, conn).await?;
+    assert_eq!(2, line.version);
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!(order, stored);
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)
+        .await?;
+    assert_eq!(line, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_update_a_child_of_an_outdated_parent(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    let mut outdated_order = order.clone();
+    order.touch_versioned(conn)?;
+
+    line.body = "updated text".to_owned();
+    let result = line
+        .update_versioned_with_parent(&mut outdated_order, conn);
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    assert_eq!(1, line.version);
+    assert_eq!(1, outdated_order.version);
+
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_update_a_child_of_an_outdated_parent(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    let mut outdated_order = order.clone();
+    order.touch_versioned(conn).await?;
+
+    line.body = "updated text".to_owned();
+    let result = line
+        .update_versioned_with_parent(&mut outdated_order, conn)
+        .await;
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    assert_eq!(1, line.version);
+    assert_eq!(1, outdated_order.version);
+
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)
+        .await?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_delete_a_child_and_touch_its_parent(
+    _logger: (),
+    mut mysql: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    line.delete_versioned_with_parent(&mut order, conn)?;
+    assert_eq!(2, order.version);
+
+    let count: i64 = diesel::QueryDsl::count(schema::order_lines::table)
+        .get_result(conn)?;
+    assert_eq!(0, count);
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_delete_a_child_and_touch_its_parent(
+    _logger: (),
+    #[future] mysql: AsyncMysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut mysql.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    line.delete_versioned_with_parent(&mut order, conn).await?;
+    assert_eq!(2, order.version);
+
+    let count: i64 = diesel::QueryDsl::count(schema::order_lines::table)
+        .get_result(conn)
+        .await?;
+    assert_eq!(0, count);
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+///
+/// Migration adding the version column to `adopted`. MySQL commits DDL implicitly, so it can't be
+/// rolled back with the test transaction. The migration is reverted and the rows are deleted on
+/// drop instead, also if the test fails.
+///
+struct Adoption {
+    conn: diesel::MysqlConnection,
+    migration: SqlMigration,
+    applied: bool,
+}
+
+impl Adoption {
+    fn new(conn: diesel::MysqlConnection) -> Self {
+        let column = AddVersionColumn::new("adopted", "version").index();
+        let migration = SqlMigration::new(
+            "20261018140000",
+            column.mysql_up(),
+            column.mysql_down(),
+        );
+        Self {
+            conn,
+            migration,
+            applied: false,
+        }
+    }
+
+    fn run(&mut self) {
+        self.conn.run_migration(&self.migration).unwrap();
+        self.applied = true;
+    }
+
+    fn revert(&mut self) {
+        self.conn.revert_migration(&self.migration).unwrap();
+        self.applied = false;
+    }
+}
+
+impl Drop for Adoption {
+    fn drop(&mut self) {
+        let _ = diesel::RunQueryDsl::execute(diesel::delete(schema::adopted::table), &mut self.conn);
+        if self.applied {
+            let _ = self.conn.revert_migration(&self.migration);
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_add_a_version_column(
+    _logger: (),
+    mysql_ddl: MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    let mut adoption = Adoption::new(mysql_ddl);
+    diesel::insert_into(schema::adopted::table)
+        .values((schema::adopted::id.eq(1), schema::adopted::body.eq("initial text")))
+        .execute(&mut adoption.conn)?;
+    adoption.run();
+    let conn = &mut adoption.conn;
+    let mut entry = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)?;
+    assert_eq!(1, entry.version);
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(2, entry.version);
+
+    adoption.revert();
+    let result = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(&mut adoption.conn);
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_add_a_version_column(
+    _logger: (),
+    mysql_ddl: diesel::MysqlConnection,
+) -> Result<(), Box<dyn Error>> {
+    use diesel_async::AsyncConnection;
+
+    // the migration runs by the sync connection like by diesel_migrations, the versioned write by
+    // an async connection without a test transaction, which would lock the table
+    let mut adoption = Adoption::new(mysql_ddl);
+    let conn = &mut AsyncMysqlConnection::establish(&fixtures::mysql_url()).await?;
+    diesel::insert_into(schema::adopted::table)
+        .values((schema::adopted::id.eq(1), schema::adopted::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    adoption.run();
+    let mut entry = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)
+        .await?;
+    assert_eq!(1, entry.version);
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(2, entry.version);
+
+    adoption.revert();
+    let result = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)
+        .await;
+    assert!(result.is_err());
+    Ok(())
+}
@@ -1,19 +1,36 @@
-use std::error::Error;
+use std::{borrow::Cow, error::Error};
 
 #[cfg(feature = "sync")]
-use diesel::RunQueryDsl;
+use diesel::{RunQueryDsl, connection::SimpleConnection};
 use diesel::{
-    ExpressionMethods, SelectableHelper, SqliteConnection, query_dsl::methods::FilterDsl,
+    ExpressionMethods, SelectableHelper, SqliteConnection, query_dsl::methods::{FilterDsl, FindDsl, OrderDsl, SelectDsl},
     result::DatabaseErrorKind,
 };
 #[cfg(feature = "async")]
-use diesel_async::{RunQueryDsl, sync_connection_wrapper::SyncConnectionWrapper};
+use diesel_async::{RunQueryDsl, SimpleAsyncConnection, sync_connection_wrapper::SyncConnectionWrapper};
 #[cfg(feature = "sync")]
-use diesel_versioning::Versioned;
+use diesel_versioning::{SoftDelete, UnitOfWork, VersionHistory, Versioned, VersionedChild};
 #[cfg(feature = "async")]
-use diesel_versioning::VersionedAsync;
+use diesel_versioning::{
+    SoftDeleteAsync, UnitOfWorkAsync, VersionHistoryAsync, VersionedAsync, VersionedChildAsync,
+};
+use diesel_versioning::{
+    Conflict, OperationKind,
+    migration::{AddVersionColumn, VersionTrigger},
+};
+#[cfg(feature = "sync")]
+use diesel_migrations::MigrationHarness;
+#[cfg(feature = "sync")]
+use diesel_versioning::{
+    lint::{LintIssue, VersionLint},
+    migration::SqlMigration,
+};
 use diesel_versioning_async_example::{
-    model::{NewSimpleEntry, SimpleEntry},
+    facade::FacadeEntry,
+    model::{
+        AdoptedEntry, AnyBackendEntry, AuditedEntry, AuditedGenericEntry, Body, BorrowedEntry, DeletableDocument, Document, GenericDocument, GenericEntry, LegacyEntry, Markdown, NewDocument, NewNote, NewSimpleEntry, Note, Order, OrderLine, OutboxEvent, PublishedEntry, SimpleEntry,
+        SimpleTupleEntry, TenantEntry,
+    },
     schema::{self},
 };
 use fixtures::{sqlite, logger};
@@ -308,3 +325,2967 @@ async fn it_should_fail_if_delete_a_updated_entry(
     assert_eq!(2, entry.version);
     Ok(())
 }
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_a_tuple_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleTupleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleTupleEntry::as_returning())
+        .get_result(conn)?;
+
+    entry.2 = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(2, entry.1);
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(entry.0))
+        .first::<SimpleTupleEntry>(conn)?;
+    assert_eq!("updated text", &entry.2);
+    assert_eq!(2, entry.1);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_a_tuple_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleTupleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleTupleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+
+    entry.2 = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(2, entry.1);
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(entry.0))
+        .first::<SimpleTupleEntry>(conn)
+        .await?;
+    assert_eq!("updated text", &entry.2);
+    assert_eq!(2, entry.1);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_delete_an_updated_tuple_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleTupleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleTupleEntry::as_returning())
+        .get_result(conn)?;
+    let mut second_entry = first_entry.clone();
+    first_entry.2 = "updated text on first".to_owned();
+    first_entry.update_versioned(conn)?;
+
+    let result = second_entry.delete_versioned(conn);
+
+    match result {
+        Ok(_) => panic!("expected error on delete"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.0))
+        .first::<SimpleTupleEntry>(conn)?;
+    assert_eq!("updated text on first", &entry.2);
+    assert_eq!(2, entry.1);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_delete_an_updated_tuple_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleTupleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleTupleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+    first_entry.2 = "updated text on first".to_owned();
+    first_entry.update_versioned(conn).await?;
+
+    let result = second_entry.delete_versioned(conn).await;
+
+    match result {
+        Ok(_) => panic!("expected error on delete"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().starts_with("optimistic locking:"),
+                "expected CheckViolation Error message start with 'optimistic locking:', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.0))
+        .first::<SimpleTupleEntry>(conn)
+        .await?;
+    assert_eq!("updated text on first", &entry.2);
+    assert_eq!(2, entry.1);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry_with_composite_key(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let other = TenantEntry {
+        tenant_id: 2,
+        ..entry.clone()
+    };
+    for entry in [&entry, &other] {
+        diesel::insert_into(schema::tenant::table)
+            .values(entry)
+            .execute(conn)?;
+    }
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+
+    let mut entries = schema::tenant::table
+        .filter(schema::tenant::id.eq(7))
+        .load::<TenantEntry>(conn)?;
+    entries.sort_by_key(|entry| entry.tenant_id);
+    assert_eq!(vec![entry, other], entries);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_with_composite_key(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let other = TenantEntry {
+        tenant_id: 2,
+        ..entry.clone()
+    };
+    for entry in [&entry, &other] {
+        diesel::insert_into(schema::tenant::table)
+            .values(entry)
+            .execute(conn)
+            .await?;
+    }
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+
+    let mut entries = schema::tenant::table
+        .filter(schema::tenant::id.eq(7))
+        .load::<TenantEntry>(conn)
+        .await?;
+    entries.sort_by_key(|entry| entry.tenant_id);
+    assert_eq!(vec![entry, other], entries);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_report_composite_key_on_conflict(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut first_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&first_entry)
+        .execute(conn)?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn)?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn);
+
+    match result {
+        Ok(_) => panic!("expected error on update"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().contains("(1, 7)"),
+                "expected CheckViolation Error message to contain the key '(1, 7)', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let result = second_entry.delete_versioned(conn);
+    assert!(result.is_err(), "expected error on delete");
+    let entry = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)?;
+    assert_eq!(first_entry, entry);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_report_composite_key_on_conflict(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut first_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&first_entry)
+        .execute(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+    first_entry.body = "updated text on first".to_owned();
+    first_entry.update_versioned(conn).await?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn).await;
+
+    match result {
+        Ok(_) => panic!("expected error on update"),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)) => {
+            assert!(
+                info.message().contains("(1, 7)"),
+                "expected CheckViolation Error message to contain the key '(1, 7)', but found: {}",
+                info.message()
+            );
+        }
+        Err(err) => {
+            panic!("got unexpected error: {:?}", err);
+        }
+    };
+    let result = second_entry.delete_versioned(conn).await;
+    assert!(result.is_err(), "expected error on delete");
+    let entry = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)
+        .await?;
+    assert_eq!(first_entry, entry);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry_with_lifetime(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: BorrowedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(BorrowedEntry::as_returning())
+        .get_result(conn)?;
+
+    let text = String::from("updated text");
+    entry.body = Cow::Borrowed(&text);
+    entry.update_versioned(conn)?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(entry.id))
+        .first::<BorrowedEntry>(conn)?;
+    assert_eq!("updated text", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_with_lifetime(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: BorrowedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(BorrowedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+
+    let text = String::from("updated text");
+    entry.body = Cow::Borrowed(&text);
+    entry.update_versioned(conn).await?;
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(entry.id))
+        .first::<BorrowedEntry>(conn)
+        .await?;
+    assert_eq!("updated text", &entry.body);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry_without_check_for_backend(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: AnyBackendEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AnyBackendEntry::as_returning())
+        .get_result(conn)?;
+    let mut second_entry = first_entry.clone();
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn)?;
+    let result = second_entry.delete_versioned(conn);
+    assert!(result.is_err(), "expected error on delete");
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<AnyBackendEntry>(conn)?;
+    assert_eq!(first_entry, entry);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_without_check_for_backend(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: AnyBackendEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AnyBackendEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn).await?;
+    let result = second_entry.delete_versioned(conn).await;
+    assert!(result.is_err(), "expected error on delete");
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<AnyBackendEntry>(conn)
+        .await?;
+    assert_eq!(first_entry, entry);
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_unversioned_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(Some(100), entry.version);
+    entry.update_versioned(conn)?;
+    assert_eq!(Some(101), entry.version);
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_unversioned_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(Some(100), entry.version);
+    entry.update_versioned(conn).await?;
+    assert_eq!(Some(101), entry.version);
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_update_an_unversioned_entry_twice(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+    let mut second_entry = entry.clone();
+    entry.body = "updated text on first".to_owned();
+    entry.update_versioned(conn)?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn);
+    assert!(result.is_err(), "expected error on update");
+    assert_eq!(None, second_entry.version);
+    let result = second_entry.delete_versioned(conn);
+    assert!(result.is_err(), "expected error on delete");
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_update_an_unversioned_entry_twice(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+    let mut second_entry = entry.clone();
+    entry.body = "updated text on first".to_owned();
+    entry.update_versioned(conn).await?;
+
+    second_entry.body = "updated text on second".to_owned();
+    let result = second_entry.update_versioned(conn).await;
+    assert!(result.is_err(), "expected error on update");
+    assert_eq!(None, second_entry.version);
+    let result = second_entry.delete_versioned(conn).await;
+    assert!(result.is_err(), "expected error on delete");
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_delete_an_unversioned_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    use diesel::OptionalExtension;
+
+    let conn = &mut sqlite;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)?;
+    assert_eq!(None, entry.version);
+
+    entry.delete_versioned(conn)?;
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .optional()?;
+    assert_eq!(None, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_delete_an_unversioned_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    use diesel::OptionalExtension;
+
+    let conn = &mut sqlite.await;
+    diesel::insert_into(schema::legacy::table)
+        .values((schema::legacy::id.eq(1), schema::legacy::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await?;
+    assert_eq!(None, entry.version);
+
+    entry.delete_versioned(conn).await?;
+
+    let stored = schema::legacy::table
+        .find(1)
+        .first::<LegacyEntry>(conn)
+        .await
+        .optional()?;
+    assert_eq!(None, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_with_sync_and_async_connection(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    use diesel::Connection;
+    use diesel_migrations::MigrationHarness;
+    use diesel_versioning::Versioned;
+
+    let sync_conn = &mut SqliteConnection::establish(":memory:")?;
+    sync_conn.run_pending_migrations(fixtures::MIGRATIONS).unwrap();
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::RunQueryDsl::get_result(
+        diesel::insert_into(schema::simple::table)
+            .values((&entry, schema::simple::version.eq(1)))
+            .returning(SimpleEntry::as_returning()),
+        sync_conn,
+    )?;
+    entry.body = "updated text".to_owned();
+    Versioned::update_versioned(&mut entry, sync_conn)?;
+    assert_eq!(2, entry.version);
+
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::simple::table
+        .find(1)
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!(1, entry.version);
+    VersionedAsync::update_versioned(&mut entry, conn).await?;
+    assert_eq!(2, entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_an_entry_through_a_facade(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: FacadeEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(FacadeEntry::as_returning())
+        .get_result(conn)?;
+    let mut second_entry = first_entry.clone();
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn)?;
+    let result = second_entry.update_versioned(conn);
+    assert!(result.is_err(), "expected error on update");
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<FacadeEntry>(conn)?;
+    assert_eq!(first_entry, entry);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_through_a_facade(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: FacadeEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(FacadeEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut second_entry = first_entry.clone();
+
+    first_entry.body = "updated text".to_owned();
+    first_entry.update_versioned(conn).await?;
+    let result = second_entry.update_versioned(conn).await;
+    assert!(result.is_err(), "expected error on update");
+
+    let entry = schema::simple::table
+        .filter(schema::simple::id.eq(first_entry.id))
+        .first::<FacadeEntry>(conn)
+        .await?;
+    assert_eq!(first_entry, entry);
+    Ok(())
+}
+
+/// Updates the entity within a spawned task, which requires the future of a generic entity and
+/// connection to be Send.
+#[cfg(feature = "async")]
+async fn update_in_task<E, CONN>(
+    mut entity: E,
+    mut conn: CONN,
+) -> Result<(E, CONN), diesel::result::Error>
+where
+    E: VersionedAsync<CONN, diesel::sqlite::Sqlite> + Send + 'static,
+    CONN: diesel_async::AsyncConnection<Backend = diesel::sqlite::Sqlite> + Send + 'static,
+{
+    tokio::spawn(async move {
+        entity.update_versioned(&mut conn).await?;
+        Ok((entity, conn))
+    })
+    .await
+    .expect("task panicked")
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_an_entry_in_a_spawned_task(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let mut conn = sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(&mut conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+
+    let (entry, mut conn) = update_in_task(entry, conn).await?;
+    assert_eq!(2, entry.version);
+    let stored = schema::simple::table
+        .filter(schema::simple::id.eq(entry.id))
+        .first::<SimpleEntry>(&mut conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_different_entities_by_trait_objects(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    use diesel_versioning::DynVersioned;
+
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let simple_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&tenant_entry)
+        .execute(conn)?;
+
+    let mut entities: Vec<Box<dyn DynVersioned<SqliteConnection>>> =
+        vec![Box::new(simple_entry.clone()), Box::new(tenant_entry)];
+    for entity in &mut entities {
+        entity.update_versioned_dyn(conn)?;
+        entity.touch_versioned_dyn(conn)?;
+    }
+    let mut outdated: Box<dyn DynVersioned<SqliteConnection>> = Box::new(simple_entry);
+    let result = outdated.delete_versioned_dyn(conn);
+    assert!(result.is_err(), "expected error on delete");
+
+    let simple_entry = schema::simple::table.find(1).first::<SimpleEntry>(conn)?;
+    assert_eq!(3, simple_entry.version);
+    let tenant_entry = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)?;
+    assert_eq!(3, tenant_entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_different_entities_by_trait_objects(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    use diesel_versioning::DynVersionedAsync;
+
+    type Conn = SyncConnectionWrapper<SqliteConnection>;
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let simple_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::tenant::table)
+        .values(&tenant_entry)
+        .execute(conn)
+        .await?;
+
+    let mut entities: Vec<Box<dyn DynVersionedAsync<Conn>>> =
+        vec![Box::new(simple_entry.clone()), Box::new(tenant_entry)];
+    for entity in &mut entities {
+        entity.update_versioned_dyn(conn).await?;
+        entity.touch_versioned_dyn(conn).await?;
+    }
+    let mut outdated: Box<dyn DynVersionedAsync<Conn>> = Box::new(simple_entry);
+    let result = outdated.delete_versioned_dyn(conn).await;
+    assert!(result.is_err(), "expected error on delete");
+
+    let simple_entry = schema::simple::table
+        .find(1)
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!(3, simple_entry.version);
+    let tenant_entry = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)
+        .await?;
+    assert_eq!(3, tenant_entry.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_commit_a_unit_of_work(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let mut second_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+
+    first_entry.body = "updated text".to_owned();
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.register_new(schema::tenant::table, &tenant_entry);
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_deleted(&mut second_entry);
+    unit_of_work.commit(conn)?;
+    assert_eq!(2, first_entry.version);
+
+    let entries = schema::simple::table.load::<SimpleEntry>(conn)?;
+    assert_eq!(vec![first_entry], entries);
+    let stored = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)?;
+    assert_eq!(tenant_entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_commit_a_unit_of_work(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut second_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+
+    first_entry.body = "updated text".to_owned();
+    let mut unit_of_work = UnitOfWorkAsync::new();
+    unit_of_work.register_new(schema::tenant::table, &tenant_entry);
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_deleted(&mut second_entry);
+    unit_of_work.commit(conn).await?;
+    assert_eq!(2, first_entry.version);
+
+    let entries = schema::simple::table.load::<SimpleEntry>(conn).await?;
+    assert_eq!(vec![first_entry], entries);
+    let stored = schema::tenant::table
+        .find((1, 7))
+        .first::<TenantEntry>(conn)
+        .await?;
+    assert_eq!(tenant_entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_roll_back_a_unit_of_work_on_conflict(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let mut second_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let mut outdated_entry = second_entry.clone();
+    second_entry.update_versioned(conn)?;
+
+    first_entry.body = "updated text".to_owned();
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.register_new(schema::tenant::table, &tenant_entry);
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    let error = unit_of_work
+        .commit(conn)
+        .expect_err("expected a version conflict");
+    let operation = error.operation().expect("failed operation");
+    assert_eq!(OperationKind::Update, operation.kind());
+    assert_eq!(2, operation.index());
+    assert!(operation.type_name().ends_with("SimpleEntry"));
+    assert!(matches!(
+        error.error(),
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, _)
+    ));
+    // the versions are restored, so the entities can be written again
+    assert_eq!(1, first_entry.version);
+    assert_eq!(1, outdated_entry.version);
+
+    let stored = schema::simple::table
+        .find(first_entry.id)
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    let count: i64 = diesel::QueryDsl::count(schema::tenant::table).get_result(conn)?;
+    assert_eq!(0, count);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_roll_back_a_unit_of_work_on_conflict(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut first_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut second_entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let tenant_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let mut outdated_entry = second_entry.clone();
+    second_entry.update_versioned(conn).await?;
+
+    first_entry.body = "updated text".to_owned();
+    let mut unit_of_work = UnitOfWorkAsync::new();
+    unit_of_work.register_new(schema::tenant::table, &tenant_entry);
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    let error = unit_of_work
+        .commit(conn)
+        .await
+        .expect_err("expected a version conflict");
+    let operation = error.operation().expect("failed operation");
+    assert_eq!(OperationKind::Update, operation.kind());
+    assert_eq!(2, operation.index());
+    assert!(operation.type_name().ends_with("SimpleEntry"));
+    assert!(matches!(
+        error.error(),
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, _)
+    ));
+    // the versions are restored, so the entities can be written again
+    assert_eq!(1, first_entry.version);
+    assert_eq!(1, outdated_entry.version);
+
+    let stored = schema::simple::table
+        .find(first_entry.id)
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    let count: i64 = diesel::QueryDsl::count(schema::tenant::table).get_result(conn).await?;
+    assert_eq!(0, count);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_touch_an_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    order.body = "unsaved text".to_owned();
+    order.touch_versioned(conn)?;
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!("order", &stored.body);
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_touch_an_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    order.body = "unsaved text".to_owned();
+    order.touch_versioned(conn).await?;
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!("order", &stored.body);
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_update_a_child_and_touch_its_parent(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    line.body = "updated text".to_owned();
+    line.update_versioned_with_parent(&mut order, conn)?;
+    assert_eq!(2, line.version);
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!(order, stored);
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)?;
+    assert_eq!(line, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_update_a_child_and_touch_its_parent(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    line.body = "updated text".to_owned();
+    line.update_versioned_with_parent(&mut order, conn).await?;
+    assert_eq!(2, line.version);
+    assert_eq!(2, order.version);
+
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!(order, stored);
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)
+        .await?;
+    assert_eq!(line, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_fail_if_update_a_child_of_an_outdated_parent(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    let mut outdated_order = order.clone();
+    order.touch_versioned(conn)?;
+
+    line.body = "updated text".to_owned();
+    let result = line
+        .update_versioned_with_parent(&mut outdated_order, conn);
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    assert_eq!(1, line.version);
+    assert_eq!(1, outdated_order.version);
+
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_fail_if_update_a_child_of_an_outdated_parent(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    let mut outdated_order = order.clone();
+    order.touch_versioned(conn).await?;
+
+    line.body = "updated text".to_owned();
+    let result = line
+        .update_versioned_with_parent(&mut outdated_order, conn)
+        .await;
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    assert_eq!(1, line.version);
+    assert_eq!(1, outdated_order.version);
+
+    let stored = schema::order_lines::table
+        .find(line.id)
+        .first::<OrderLine>(conn)
+        .await?;
+    assert_eq!("initial text", &stored.body);
+    assert_eq!(1, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_delete_a_child_and_touch_its_parent(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)?;
+
+    line.delete_versioned_with_parent(&mut order, conn)?;
+    assert_eq!(2, order.version);
+
+    let count: i64 = diesel::QueryDsl::count(schema::order_lines::table)
+        .get_result(conn)?;
+    assert_eq!(0, count);
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)?;
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_delete_a_child_and_touch_its_parent(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut order = Order {
+        id: 1,
+        version: 1,
+        body: "order".to_owned(),
+    };
+    let mut line = OrderLine {
+        id: 1,
+        order_id: 1,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    diesel::insert_into(schema::orders::table)
+        .values(&order)
+        .execute(conn)
+        .await?;
+    diesel::insert_into(schema::order_lines::table)
+        .values(&line)
+        .execute(conn)
+        .await?;
+
+    line.delete_versioned_with_parent(&mut order, conn).await?;
+    assert_eq!(2, order.version);
+
+    let count: i64 = diesel::QueryDsl::count(schema::order_lines::table)
+        .get_result(conn)
+        .await?;
+    assert_eq!(0, count);
+    let stored = schema::orders::table
+        .find(order.id)
+        .first::<Order>(conn)
+        .await?;
+    assert_eq!(2, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_reject_an_update_bypassing_the_version(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let trigger = VersionTrigger::new("simple", "version");
+    conn.batch_execute(&trigger.sqlite_up())?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let result = diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn);
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(_, _))
+    ));
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(2, entry.version);
+
+    conn.batch_execute(&trigger.sqlite_down())?;
+    diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_reject_an_update_bypassing_the_version(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let trigger = VersionTrigger::new("simple", "version");
+    conn.batch_execute(&trigger.sqlite_up()).await?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let result = diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn)
+        .await;
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(_, _))
+    ));
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(2, entry.version);
+
+    conn.batch_execute(&trigger.sqlite_down()).await?;
+    diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_increment_the_version_by_trigger(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let trigger = VersionTrigger::new("simple", "version").increment();
+    conn.batch_execute(&trigger.sqlite_up())?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let mut outdated_entry = entry.clone();
+    diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn)?;
+    let mut entry = schema::simple::table
+        .find(entry.id)
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!(2, entry.version);
+    assert_eq!("bypassed text", &entry.body);
+
+    outdated_entry.body = "outdated text".to_owned();
+    let result = outdated_entry.update_versioned(conn);
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<SimpleEntry>(conn)?;
+    assert_eq!(entry, stored);
+    assert_eq!(3, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_increment_the_version_by_trigger(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let trigger = VersionTrigger::new("simple", "version").increment();
+    conn.batch_execute(&trigger.sqlite_up()).await?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut outdated_entry = entry.clone();
+    diesel::update(schema::simple::table.find(entry.id))
+        .set(schema::simple::body.eq("bypassed text"))
+        .execute(conn)
+        .await?;
+    let mut entry = schema::simple::table
+        .find(entry.id)
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!(2, entry.version);
+    assert_eq!("bypassed text", &entry.body);
+
+    outdated_entry.body = "outdated text".to_owned();
+    let result = outdated_entry.update_versioned(conn).await;
+    assert!(matches!(
+        result,
+        Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::CheckViolation,
+            _
+        ))
+    ));
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<SimpleEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    assert_eq!(3, stored.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_add_a_version_column(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    diesel::insert_into(schema::adopted::table)
+        .values((schema::adopted::id.eq(1), schema::adopted::body.eq("initial text")))
+        .execute(conn)?;
+    let column = AddVersionColumn::new("adopted", "version").index();
+    let migration = SqlMigration::new(
+        "20261018140000",
+        column.sqlite_up(),
+        column.sqlite_down(),
+    );
+    conn.run_migration(&migration).unwrap();
+    let mut entry = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)?;
+    assert_eq!(1, entry.version);
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    assert_eq!(2, entry.version);
+
+    conn.revert_migration(&migration).unwrap();
+    let result = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn);
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_add_a_version_column(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    diesel::insert_into(schema::adopted::table)
+        .values((schema::adopted::id.eq(1), schema::adopted::body.eq("initial text")))
+        .execute(conn)
+        .await?;
+    let column = AddVersionColumn::new("adopted", "version").index();
+    conn.batch_execute(&column.sqlite_up()).await?;
+    let mut entry = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)
+        .await?;
+    assert_eq!(1, entry.version);
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    assert_eq!(2, entry.version);
+
+    conn.batch_execute(&column.sqlite_down()).await?;
+    let result = schema::adopted::table
+        .find(1)
+        .first::<AdoptedEntry>(conn)
+        .await;
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_list_the_tables_lacking_a_version_column(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    // the history tables and the outbox table aren't versioned themselves
+    let findings = VersionLint::new("version")
+        .ignore("note_history")
+        .ignore("outbox")
+        .ignore("simple_history")
+        .sqlite(conn)?;
+    let findings: Vec<_> = findings
+        .iter()
+        .map(|finding| (finding.table.as_str(), finding.issue))
+        .collect();
+    assert_eq!(
+        vec![
+            ("adopted", LintIssue::MissingColumn),
+            ("legacy", LintIssue::NullableColumn),
+            ("order_lines", LintIssue::MissingDefault),
+            ("orders", LintIssue::MissingDefault),
+            ("simple", LintIssue::MissingDefault),
+            ("tenant", LintIssue::MissingDefault),
+        ],
+        findings
+    );
+
+    conn.batch_execute(&AddVersionColumn::new("adopted", "version").sqlite_up())?;
+    let findings = VersionLint::new("version")
+        .ignore("legacy")
+        .ignore("note_history")
+        .ignore("order_lines")
+        .ignore("orders")
+        .ignore("outbox")
+        .ignore("simple_history")
+        .sqlite(conn)?;
+    let findings: Vec<_> = findings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        vec!["simple: version column without default", "tenant: version column without default"],
+        findings
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_keep_the_history_of_an_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn)?;
+    entry.touch_versioned(conn)?;
+    assert_eq!(4, entry.version);
+
+    let history = entry.load_history(conn)?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(1, "initial text"), (2, "updated text"), (3, "final text")],
+        history
+    );
+    let version = entry.load_version(2, conn)?;
+    assert_eq!("updated text", &version.body);
+    assert_eq!(2, version.version);
+    let version = entry.load_version(4, conn)?;
+    assert_eq!(entry, version);
+    let result = entry.load_version(5, conn);
+    assert_eq!(Err(diesel::result::Error::NotFound), result);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_keep_the_history_of_an_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn).await?;
+    entry.touch_versioned(conn).await?;
+    assert_eq!(4, entry.version);
+
+    let history = entry.load_history(conn).await?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(1, "initial text"), (2, "updated text"), (3, "final text")],
+        history
+    );
+    let version = entry.load_version(2, conn).await?;
+    assert_eq!("updated text", &version.body);
+    assert_eq!(2, version.version);
+    let version = entry.load_version(4, conn).await?;
+    assert_eq!(entry, version);
+    let result = entry.load_version(5, conn).await;
+    assert_eq!(Err(diesel::result::Error::NotFound), result);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_keep_the_deleted_version_in_the_history(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    entry.delete_versioned(conn)?;
+
+    let history = entry.load_history(conn)?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "initial text"), (2, "updated text")], history);
+    let version = entry.load_version(2, conn)?;
+    assert_eq!(entry, version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_keep_the_deleted_version_in_the_history(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    entry.delete_versioned(conn).await?;
+
+    let history = entry.load_history(conn).await?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "initial text"), (2, "updated text")], history);
+    let version = entry.load_version(2, conn).await?;
+    assert_eq!(entry, version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_not_write_the_history_on_a_conflict(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)?;
+    let mut stale = entry.clone();
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn);
+    assert!(result.is_err());
+    let result = stale.delete_versioned(conn);
+    assert!(result.is_err());
+
+    let history = entry.load_history(conn)?;
+    assert_eq!(1, history.len());
+    assert_eq!("initial text", &history[0].body);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_not_write_the_history_on_a_conflict(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut stale = entry.clone();
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn).await;
+    assert!(result.is_err());
+    let result = stale.delete_versioned(conn).await;
+    assert!(result.is_err());
+
+    let history = entry.load_history(conn).await?;
+    assert_eq!(1, history.len());
+    assert_eq!("initial text", &history[0].body);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_restore_a_previous_version(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn)?;
+
+    entry.restore_to_version(1, conn)?;
+    assert_eq!("initial text", &entry.body);
+    assert_eq!(4, entry.version);
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<AuditedEntry>(conn)?;
+    assert_eq!(entry, stored);
+    let history = entry.load_history(conn)?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(1, "initial text"), (2, "updated text"), (3, "final text")],
+        history
+    );
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_restore_a_previous_version(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn).await?;
+
+    entry.restore_to_version(1, conn).await?;
+    assert_eq!("initial text", &entry.body);
+    assert_eq!(4, entry.version);
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<AuditedEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    let history = entry.load_history(conn).await?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(1, "initial text"), (2, "updated text"), (3, "final text")],
+        history
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_not_restore_a_stale_entry(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    let mut stale = entry.clone();
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn)?;
+
+    let result = stale.restore_to_version(1, conn);
+    assert!(result.is_err());
+    assert_eq!("updated text", &stale.body);
+    assert_eq!(2, stale.version);
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<AuditedEntry>(conn)?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_not_restore_a_stale_entry(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    let mut stale = entry.clone();
+    entry.body = "final text".to_owned();
+    entry.update_versioned(conn).await?;
+
+    let result = stale.restore_to_version(1, conn).await;
+    assert!(result.is_err());
+    assert_eq!("updated text", &stale.body);
+    assert_eq!(2, stale.version);
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<AuditedEntry>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_keep_a_soft_deleted_document(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)?;
+    document.delete_versioned(conn)?;
+    assert_eq!(2, document.version);
+
+    let (version, deleted) = schema::document::table
+        .find(document.id)
+        .select((
+            schema::document::version,
+            schema::document::deleted_at.is_not_null(),
+        ))
+        .first::<(i32, bool)>(conn)?;
+    assert_eq!(2, version);
+    assert!(deleted);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_keep_a_soft_deleted_document(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)
+        .await?;
+    document.delete_versioned(conn).await?;
+    assert_eq!(2, document.version);
+
+    let (version, deleted) = schema::document::table
+        .find(document.id)
+        .select((
+            schema::document::version,
+            schema::document::deleted_at.is_not_null(),
+        ))
+        .first::<(i32, bool)>(conn)
+        .await?;
+    assert_eq!(2, version);
+    assert!(deleted);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_report_a_soft_deleted_document(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)?;
+    let mut stale = document.clone();
+    document.delete_versioned(conn)?;
+
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn);
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(1, stale.version);
+    let result = document.update_versioned(conn);
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(2, document.version);
+    let result = document.touch_versioned(conn);
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    let result = document.delete_versioned(conn);
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(2, document.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_report_a_soft_deleted_document(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut stale = document.clone();
+    document.delete_versioned(conn).await?;
+
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn).await;
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(1, stale.version);
+    let result = document.update_versioned(conn).await;
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(2, document.version);
+    let result = document.touch_versioned(conn).await;
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    let result = document.delete_versioned(conn).await;
+    assert_eq!(Some(Conflict::Deleted), result.as_ref().err().and_then(Conflict::of));
+    assert_eq!(2, document.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_undelete_a_soft_deleted_document(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)?;
+    let mut stale = document.clone();
+    document.delete_versioned(conn)?;
+    let result = stale.undelete_versioned(conn);
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+
+    document.undelete_versioned(conn)?;
+    assert_eq!(3, document.version);
+    let result = document.clone().undelete_versioned(conn);
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+    document.body = "updated text".to_owned();
+    document.update_versioned(conn)?;
+    assert_eq!(4, document.version);
+
+    let stored = schema::document::table
+        .find(document.id)
+        .select(Document::as_select())
+        .first(conn)?;
+    assert_eq!(document, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_undelete_a_soft_deleted_document(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut stale = document.clone();
+    document.delete_versioned(conn).await?;
+    let result = stale.undelete_versioned(conn).await;
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+
+    document.undelete_versioned(conn).await?;
+    assert_eq!(3, document.version);
+    let result = document.clone().undelete_versioned(conn).await;
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+    document.body = "updated text".to_owned();
+    document.update_versioned(conn).await?;
+    assert_eq!(4, document.version);
+
+    let stored = schema::document::table
+        .find(document.id)
+        .select(Document::as_select())
+        .first(conn)
+        .await?;
+    assert_eq!(document, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_clear_the_soft_delete_field_on_undelete(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: DeletableDocument = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(DeletableDocument::as_returning())
+        .get_result(conn)?;
+    document.delete_versioned(conn)?;
+    // the time of the database isn't read back by the delete
+    assert_eq!(None, document.deleted_at);
+    let mut document = schema::document::table
+        .find(document.id)
+        .select(DeletableDocument::as_select())
+        .first(conn)?;
+    assert!(document.deleted_at.is_some());
+
+    document.undelete_versioned(conn)?;
+    assert_eq!(None, document.deleted_at);
+    document.body = "updated text".to_owned();
+    document.update_versioned(conn)?;
+    let stored = schema::document::table
+        .find(document.id)
+        .select(DeletableDocument::as_select())
+        .first(conn)?;
+    assert_eq!(document, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_clear_the_soft_delete_field_on_undelete(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: DeletableDocument = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(DeletableDocument::as_returning())
+        .get_result(conn)
+        .await?;
+    document.delete_versioned(conn).await?;
+    // the time of the database isn't read back by the delete
+    assert_eq!(None, document.deleted_at);
+    let mut document = schema::document::table
+        .find(document.id)
+        .select(DeletableDocument::as_select())
+        .first(conn)
+        .await?;
+    assert!(document.deleted_at.is_some());
+
+    document.undelete_versioned(conn).await?;
+    assert_eq!(None, document.deleted_at);
+    document.body = "updated text".to_owned();
+    document.update_versioned(conn).await?;
+    let stored = schema::document::table
+        .find(document.id)
+        .select(DeletableDocument::as_select())
+        .first(conn)
+        .await?;
+    assert_eq!(document, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_publish_an_event_of_every_versioned_write(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: PublishedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(PublishedEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn)?;
+    entry.touch_versioned(conn)?;
+    entry.delete_versioned(conn)?;
+
+    let events = schema::outbox::table
+        .order(schema::outbox::id)
+        .select(OutboxEvent::as_select())
+        .load(conn)?;
+    // the payload of a delete is the last stored version
+    let event = |version: i64, operation: &str, stored: i64| OutboxEvent {
+        entity_type: "PublishedEntry".to_owned(),
+        entity_id: entry.id.to_string(),
+        version,
+        operation: operation.to_owned(),
+        payload: format!(r#"{{"id":{},"version":{},"body":"updated text"}}"#, entry.id, stored),
+    };
+    assert_eq!(
+        vec![
+            event(2, "update", 2),
+            event(3, "touch", 3),
+            event(4, "delete", 3),
+        ],
+        events
+    );
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_publish_an_event_of_every_versioned_write(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: PublishedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(PublishedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = "updated text".to_owned();
+    entry.update_versioned(conn).await?;
+    entry.touch_versioned(conn).await?;
+    entry.delete_versioned(conn).await?;
+
+    let events = schema::outbox::table
+        .order(schema::outbox::id)
+        .select(OutboxEvent::as_select())
+        .load(conn)
+        .await?;
+    // the payload of a delete is the last stored version
+    let event = |version: i64, operation: &str, stored: i64| OutboxEvent {
+        entity_type: "PublishedEntry".to_owned(),
+        entity_id: entry.id.to_string(),
+        version,
+        operation: operation.to_owned(),
+        payload: format!(r#"{{"id":{},"version":{},"body":"updated text"}}"#, entry.id, stored),
+    };
+    assert_eq!(
+        vec![
+            event(2, "update", 2),
+            event(3, "touch", 3),
+            event(4, "delete", 3),
+        ],
+        events
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_not_publish_an_event_of_a_conflict(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: PublishedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(PublishedEntry::as_returning())
+        .get_result(conn)?;
+    let mut stale = entry.clone();
+    entry.update_versioned(conn)?;
+
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn);
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+    let result = stale.delete_versioned(conn);
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+
+    let versions = schema::outbox::table
+        .order(schema::outbox::id)
+        .select(schema::outbox::version)
+        .load::<i64>(conn)?;
+    assert_eq!(vec![2], versions);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_not_publish_an_event_of_a_conflict(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: PublishedEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(PublishedEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut stale = entry.clone();
+    entry.update_versioned(conn).await?;
+
+    stale.body = "stale text".to_owned();
+    let result = stale.update_versioned(conn).await;
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+    let result = stale.delete_versioned(conn).await;
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+
+    let versions = schema::outbox::table
+        .order(schema::outbox::id)
+        .select(schema::outbox::version)
+        .load::<i64>(conn)
+        .await?;
+    assert_eq!(vec![2], versions);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_restore_a_soft_deleted_entity_of_a_rolled_back_unit_of_work(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let mut outdated_entry = entry.clone();
+    entry.update_versioned(conn)?;
+
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.register_deleted(&mut document);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    unit_of_work
+        .commit(conn)
+        .expect_err("expected a version conflict");
+    // the soft delete incremented the version, which is restored
+    assert_eq!(1, document.version);
+
+    document.delete_versioned(conn)?;
+    assert_eq!(2, document.version);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_restore_a_soft_deleted_entity_of_a_rolled_back_unit_of_work(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: Document = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(Document::as_returning())
+        .get_result(conn)
+        .await?;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    let mut outdated_entry = entry.clone();
+    entry.update_versioned(conn).await?;
+
+    let mut unit_of_work = UnitOfWorkAsync::new();
+    unit_of_work.register_deleted(&mut document);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    unit_of_work
+        .commit(conn)
+        .await
+        .expect_err("expected a version conflict");
+    // the soft delete incremented the version, which is restored
+    assert_eq!(1, document.version);
+
+    document.delete_versioned(conn).await?;
+    assert_eq!(2, document.version);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_only_classify_the_conflicts_of_versioned_writes(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    conn.batch_execute("CREATE TABLE checked (id INTEGER NOT NULL PRIMARY KEY CHECK (id > 0));")?;
+    let error = conn
+        .batch_execute("INSERT INTO checked (id) VALUES (0);")
+        .expect_err("expected a check violation");
+    assert!(matches!(
+        error,
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, _)
+    ));
+    assert_eq!(None, Conflict::of(&error));
+
+    // the message of a conflict isn't enough
+    let error = diesel::result::Error::DatabaseError(
+        DatabaseErrorKind::CheckViolation,
+        Box::new("optimistic locking: id 1 is deleted".to_owned()),
+    );
+    assert_eq!(None, Conflict::of(&error));
+
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: SimpleEntry = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(SimpleEntry::as_returning())
+        .get_result(conn)?;
+    let mut outdated_entry = entry.clone();
+    entry.update_versioned(conn)?;
+    let result = outdated_entry.update_versioned(conn);
+    assert_eq!(Some(Conflict::Version), result.as_ref().err().and_then(Conflict::of));
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_match_composite_keys_in_a_unit_of_work(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let mut first_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let mut second_entry = TenantEntry {
+        tenant_id: 2,
+        ..first_entry.clone()
+    };
+    for entry in [&first_entry, &second_entry] {
+        diesel::insert_into(schema::tenant::table)
+            .values(entry)
+            .execute(conn)?;
+    }
+    let mut outdated_entry = second_entry.clone();
+    second_entry.update_versioned(conn)?;
+
+    // the rows only differ by the tenant, so they are matched by the full key
+    first_entry.body = "updated text".to_owned();
+    outdated_entry.body = "stale text".to_owned();
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    let error = unit_of_work
+        .commit(conn)
+        .expect_err("expected a version conflict");
+    assert_eq!(1, error.operation().expect("failed operation").index());
+    assert_eq!(Some(Conflict::Version), Conflict::of(error.error()));
+    let diesel::result::Error::DatabaseError(_, info) = error.error() else {
+        panic!("got unexpected error: {:?}", error.error());
+    };
+    assert!(
+        info.message().contains("(2, 7)"),
+        "expected the key '(2, 7)' in the message, but found: {}",
+        info.message()
+    );
+
+    let mut unit_of_work = UnitOfWork::new();
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut second_entry);
+    unit_of_work.commit(conn)?;
+    let mut entries = schema::tenant::table
+        .filter(schema::tenant::id.eq(7))
+        .load::<TenantEntry>(conn)?;
+    entries.sort_by_key(|entry| entry.tenant_id);
+    assert_eq!(vec![first_entry, second_entry], entries);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_match_composite_keys_in_a_unit_of_work(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let mut first_entry = TenantEntry {
+        tenant_id: 1,
+        id: 7,
+        version: 1,
+        body: "initial text".to_owned(),
+    };
+    let mut second_entry = TenantEntry {
+        tenant_id: 2,
+        ..first_entry.clone()
+    };
+    for entry in [&first_entry, &second_entry] {
+        diesel::insert_into(schema::tenant::table)
+            .values(entry)
+            .execute(conn)
+            .await?;
+    }
+    let mut outdated_entry = second_entry.clone();
+    second_entry.update_versioned(conn).await?;
+
+    // the rows only differ by the tenant, so they are matched by the full key
+    first_entry.body = "updated text".to_owned();
+    outdated_entry.body = "stale text".to_owned();
+    let mut unit_of_work = UnitOfWorkAsync::new();
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut outdated_entry);
+    let error = unit_of_work
+        .commit(conn)
+        .await
+        .expect_err("expected a version conflict");
+    assert_eq!(1, error.operation().expect("failed operation").index());
+    assert_eq!(Some(Conflict::Version), Conflict::of(error.error()));
+    let diesel::result::Error::DatabaseError(_, info) = error.error() else {
+        panic!("got unexpected error: {:?}", error.error());
+    };
+    assert!(
+        info.message().contains("(2, 7)"),
+        "expected the key '(2, 7)' in the message, but found: {}",
+        info.message()
+    );
+
+    let mut unit_of_work = UnitOfWorkAsync::new();
+    unit_of_work.register_dirty(&mut first_entry);
+    unit_of_work.register_dirty(&mut second_entry);
+    unit_of_work.commit(conn).await?;
+    let mut entries = schema::tenant::table
+        .filter(schema::tenant::id.eq(7))
+        .load::<TenantEntry>(conn)
+        .await?;
+    entries.sort_by_key(|entry| entry.tenant_id);
+    assert_eq!(vec![first_entry, second_entry], entries);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_restore_a_version_with_a_null_column(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let note = NewNote {
+        body: "initial text".to_owned(),
+        remark: None,
+    };
+    let mut note: Note = diesel::insert_into(schema::note::table)
+        .values(&note)
+        .returning(Note::as_returning())
+        .get_result(conn)?;
+    note.remark = Some("remark".to_owned());
+    note.update_versioned(conn)?;
+
+    // AsChangeset would skip the remark of the first version
+    note.restore_to_version(1, conn)?;
+    assert_eq!(3, note.version);
+    assert_eq!(None, note.remark);
+    let stored = schema::note::table
+        .find(note.id)
+        .select(Note::as_select())
+        .first(conn)?;
+    assert_eq!(note, stored);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_restore_a_version_with_a_null_column(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let note = NewNote {
+        body: "initial text".to_owned(),
+        remark: None,
+    };
+    let mut note: Note = diesel::insert_into(schema::note::table)
+        .values(&note)
+        .returning(Note::as_returning())
+        .get_result(conn)
+        .await?;
+    note.remark = Some("remark".to_owned());
+    note.update_versioned(conn).await?;
+
+    // AsChangeset would skip the remark of the first version
+    note.restore_to_version(1, conn).await?;
+    assert_eq!(3, note.version);
+    assert_eq!(None, note.remark);
+    let stored = schema::note::table
+        .find(note.id)
+        .select(Note::as_select())
+        .first(conn)
+        .await?;
+    assert_eq!(note, stored);
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_write_an_entry_with_a_type_parameter(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: GenericEntry<Markdown> = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(GenericEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = Body::new("updated text");
+    entry.update_versioned(conn)?;
+    entry.touch_versioned(conn)?;
+    assert_eq!(3, entry.version);
+
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<GenericEntry<Markdown>>(conn)?;
+    assert_eq!(entry, stored);
+    entry.delete_versioned(conn)?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_write_an_entry_with_a_type_parameter(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: GenericEntry<Markdown> = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(GenericEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = Body::new("updated text");
+    entry.update_versioned(conn).await?;
+    entry.touch_versioned(conn).await?;
+    assert_eq!(3, entry.version);
+
+    let stored = schema::simple::table
+        .find(entry.id)
+        .first::<GenericEntry<Markdown>>(conn)
+        .await?;
+    assert_eq!(entry, stored);
+    entry.delete_versioned(conn).await?;
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_keep_the_history_and_events_of_an_entry_with_a_type_parameter(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedGenericEntry<Markdown> = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedGenericEntry::as_returning())
+        .get_result(conn)?;
+    entry.body = Body::new("updated text");
+    entry.update_versioned(conn)?;
+
+    let history = entry.load_history(conn)?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.0.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "initial text")], history);
+    let payloads = schema::outbox::table
+        .select(schema::outbox::payload)
+        .load::<String>(conn)?;
+    assert_eq!(
+        vec![format!(r#"{{"id":{},"version":2,"body":"updated text"}}"#, entry.id)],
+        payloads
+    );
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_keep_the_history_and_events_of_an_entry_with_a_type_parameter(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let entry = NewSimpleEntry {
+        body: "initial text".to_owned(),
+    };
+    let mut entry: AuditedGenericEntry<Markdown> = diesel::insert_into(schema::simple::table)
+        .values((&entry, schema::simple::version.eq(1)))
+        .returning(AuditedGenericEntry::as_returning())
+        .get_result(conn)
+        .await?;
+    entry.body = Body::new("updated text");
+    entry.update_versioned(conn).await?;
+
+    let history = entry.load_history(conn).await?;
+    let history: Vec<_> = history
+        .iter()
+        .map(|entry| (entry.version, entry.body.0.as_str()))
+        .collect();
+    assert_eq!(vec![(1, "initial text")], history);
+    let payloads = schema::outbox::table
+        .select(schema::outbox::payload)
+        .load::<String>(conn)
+        .await?;
+    assert_eq!(
+        vec![format!(r#"{{"id":{},"version":2,"body":"updated text"}}"#, entry.id)],
+        payloads
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
+#[rstest]
+#[test]
+fn it_should_soft_delete_a_document_with_a_type_parameter(
+    _logger: (),
+    mut sqlite: SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: GenericDocument<Markdown> = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(GenericDocument::as_returning())
+        .get_result(conn)?;
+    document.delete_versioned(conn)?;
+    document.undelete_versioned(conn)?;
+    assert_eq!(3, document.version);
+
+    let deleted = schema::document::table
+        .find(document.id)
+        .select(schema::document::deleted_at.is_not_null())
+        .first::<bool>(conn)?;
+    assert!(!deleted);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[rstest]
+#[tokio::test]
+async fn it_should_soft_delete_a_document_with_a_type_parameter(
+    _logger: (),
+    #[future] sqlite: SyncConnectionWrapper<SqliteConnection>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = &mut sqlite.await;
+    let document = NewDocument {
+        body: "initial text".to_owned(),
+    };
+    let mut document: GenericDocument<Markdown> = diesel::insert_into(schema::document::table)
+        .values(&document)
+        .returning(GenericDocument::as_returning())
+        .get_result(conn)
+        .await?;
+    document.delete_versioned(conn).await?;
+    document.undelete_versioned(conn).await?;
+    assert_eq!(3, document.version);
+
+    let deleted = schema::document::table
+        .find(document.id)
+        .select(schema::document::deleted_at.is_not_null())
+        .first::<bool>(conn)
+        .await?;
+    assert!(!deleted);
+    Ok(())
+}
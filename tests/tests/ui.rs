@@ -0,0 +1,9 @@
+#![cfg(feature = "sync")]
+//! Locks in the compile errors of the derive, see `tests/ui`. Run with `TRYBUILD=overwrite` to
+//! update the expected output.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub enum Entry {
+    Simple { id: i32, version: i32 },
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+use diesel::backend::Backend;
+use diesel::serialize::{Output, ToSql};
+use diesel::sql_types::Integer;
+use diesel::{AsChangeset, AsExpression, Identifiable};
+use diesel_versioning::Versioned;
+
+/// Key without `Debug`, which can't be named in the errors of conflicts.
+#[derive(Hash, PartialEq, Eq, AsExpression)]
+#[diesel(sql_type = Integer)]
+pub struct EntryId(i32);
+
+impl<DB: Backend> ToSql<Integer, DB> for EntryId
+where
+    i32: ToSql<Integer, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> diesel::serialize::Result {
+        self.0.to_sql(out)
+    }
+}
+
+#[derive(AsChangeset, Identifiable, Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Entry {
+    pub id: EntryId,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct Entry {
+    pub id: i32,
+    pub version: i32,
+    pub body: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+pub struct Entry(
+    #[diesel(column_name = id)] pub i32,
+    #[version] pub i32,
+);
+
+fn main() {}
@@ -0,0 +1,14 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct Entry {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    #[version]
+    #[version]
+    pub body: String,
+}
+
+fn main() {}
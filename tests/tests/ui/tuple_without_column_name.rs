@@ -0,0 +1,11 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct Entry(
+    #[diesel(column_name = id)] pub i32,
+    #[version] pub i32,
+    #[diesel(column_name = body)] pub String,
+);
+
+fn main() {}
@@ -0,0 +1,13 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+#[diesel_versioning(krate = "diesel_versioning")]
+pub struct Entry {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+pub struct XminEntry {
+    pub id: i32,
+    pub body: String,
+    #[version(xmin, history = diesel_versioning_async_example::schema::simple_history)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple, primary_key(key))]
+pub struct UnknownKeyEntry {
+    pub id: i32,
+    #[version(history = diesel_versioning_async_example::schema::simple_history)]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct TupleEntry(
+    #[diesel(column_name = id)] pub i32,
+    #[diesel(column_name = version)]
+    #[version(history = diesel_versioning_async_example::schema::simple_history)]
+    pub i32,
+    pub String,
+);
+
+fn main() {}
@@ -0,0 +1,12 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+pub struct XminEntry {
+    pub id: i32,
+    pub body: String,
+    #[version(xmin, soft_delete = deleted_at)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+fn main() {}
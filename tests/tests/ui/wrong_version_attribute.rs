@@ -0,0 +1,21 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct Entry {
+    pub id: i32,
+    #[version(initial = 1)]
+    pub version: i32,
+    pub body: String,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::legacy)]
+pub struct LegacyEntry {
+    pub id: i32,
+    #[version(start = 1)]
+    pub version: Option<i32>,
+    pub body: String,
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use diesel::prelude::*;
+use diesel_versioning::Versioned;
+
+#[derive(Identifiable, AsChangeset, Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::legacy)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NullableColumnEntry {
+    pub id: i32,
+    #[version]
+    pub version: i32,
+    pub body: String,
+}
+
+/// The missing column is reported by `AsChangeset` of diesel, and not again by `Versioned`.
+#[derive(Identifiable, AsChangeset, Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct MissingColumnEntry {
+    pub id: i32,
+    #[version]
+    pub revision: i32,
+    pub body: String,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use diesel::prelude::*;
+use diesel_versioning::Versioned;
+
+/// The `Text` column is rejected by `AsChangeset` of diesel, and by `Versioned` only once.
+#[derive(Identifiable, AsChangeset, Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::simple)]
+pub struct TextColumnEntry {
+    pub id: i32,
+    #[version]
+    #[diesel(column_name = body)]
+    pub version: i32,
+}
+
+fn main() {}
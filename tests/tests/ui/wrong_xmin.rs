@@ -0,0 +1,30 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+pub struct Entry {
+    pub id: i32,
+    pub body: String,
+    #[version(xmin)]
+    pub xmin: u32,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SqliteEntry {
+    pub id: i32,
+    pub body: String,
+    #[version(xmin)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+pub struct KeyOnlyEntry {
+    pub id: i32,
+    #[version(xmin)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+use diesel_versioning::Versioned;
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+#[diesel(treat_none_as_null = true)]
+pub struct Entry {
+    pub id: i32,
+    #[diesel(skip_update)]
+    pub body: String,
+    #[version(xmin)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+#[derive(Versioned)]
+#[diesel(table_name = diesel_versioning_async_example::schema::plain)]
+pub struct SerializedEntry {
+    pub id: i32,
+    #[diesel(serialize_as = String)]
+    pub body: Body,
+    #[version(xmin)]
+    pub xmin: diesel_versioning::pg::TransactionId,
+}
+
+pub struct Body(String);
+
+impl From<Body> for String {
+    fn from(body: Body) -> Self {
+        body.0
+    }
+}
+
+fn main() {}